@@ -1,24 +1,160 @@
 use regex::Regex;
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
 use std::error::Error;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 use tokio::runtime::Runtime;
-use std::io::Write;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 use indicatif::{ProgressBar, ProgressStyle};
 use std::fs::OpenOptions;
 
 use crate::nuclear_data_amdc_2016::Isotope;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub energy: f64,              // MeV
+    pub uncertainty: Option<f64>, // MeV, None for limits or missing values
+    pub jpi: String,              // raw NNDC spin-parity, empty when not assigned
+    #[serde(default)]
+    pub half_life: String, // raw NNDC T1/2, e.g. "6.346 s 2", empty when not given
+}
+
+// NDS style uncertainties are given in units of the last digit of the value, e.g. "1368.672 5" is 1368.672(5) keV
+fn parse_uncertainty(value: &str, remainder: &str) -> Option<f64> {
+    let digits = remainder.split_whitespace().next()?;
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None; // LT, GT, AP, SY, ... are limits or estimates
+    }
+
+    let (mantissa, exponent) = match value.split_once('E') {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+        None => (value, 0),
+    };
+    let decimals = mantissa.split_once('.').map_or(0, |(_, frac)| frac.len()) as i32;
+
+    let uncertainty = digits.parse::<f64>().ok()? * 10f64.powi(exponent - decimals);
+    Some(uncertainty / 1000.0) // keV -> MeV
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExcitationResponse {
-    excitation_levels: Vec<f64>,
+    excitation_levels: Vec<Level>,
+}
+
+// NNDC answered 429 Too Many Requests
+#[derive(Debug)]
+struct RateLimited {
+    retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.retry_after {
+            Some(retry_after) => write!(
+                f,
+                "Rate limited by NNDC, retry after {} s",
+                retry_after.as_secs()
+            ),
+            None => write!(f, "Rate limited by NNDC"),
+        }
+    }
+}
+
+impl Error for RateLimited {}
+
+// Delays between attempts when NNDC returns an error or a partial page
+const RETRY_DELAYS_MS: [u64; 3] = [250, 500, 1000];
+
+const OUTPUT_FILE: &str = "excitation_levels.csv";
+
+// keV precision, what the bundled levels were written with
+pub const DEFAULT_PRECISION: usize = 3;
+
+// Isotope names already written by an earlier, possibly interrupted, run
+fn processed_isotopes(path: &str) -> HashSet<String> {
+    read_levels_csv(path)
+        .map(|rows| rows.into_iter().map(|(isotope, _)| isotope).collect())
+        .unwrap_or_default()
+}
+
+// One `isotope,levels` row, the levels (MeV) are comma separated inside a single quoted field
+fn write_levels_record<W: std::io::Write>(
+    writer: &mut csv::Writer<W>,
+    isotope: &str,
+    levels: &[Level],
+) -> csv::Result<()> {
+    let levels = levels
+        .iter()
+        .map(|l| l.energy.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    writer.write_record([isotope, levels.as_str()])?;
+    writer.flush()?;
+    Ok(())
+}
+
+// (isotope, levels in MeV), one row of the excitation_levels.csv written by process_isotopes
+pub type IsotopeLevels = (String, Vec<f64>);
+
+// Reads the output of process_isotopes back
+pub fn read_levels_csv(path: &str) -> Result<Vec<IsotopeLevels>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let isotope = record.get(0).unwrap_or_default().to_string();
+        let levels = record
+            .get(1)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|level| !level.is_empty())
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.push((isotope, levels));
+    }
+    Ok(rows)
+}
+
+// NuDat names nuclei as mass number + element symbol, e.g. "12C" or "208Pb"
+pub fn nndc_name(isotope: &Isotope) -> Option<String> {
+    // the free neutron has no level scheme
+    if isotope.z == 0 {
+        return None;
+    }
+
+    // the mass table pads some symbols and NuDat wants them capitalized
+    let mut symbol = isotope.el.trim().chars();
+    let first = symbol.next()?.to_ascii_uppercase();
+    Some(format!(
+        "{}{}{}",
+        isotope.a,
+        first,
+        symbol.as_str().to_ascii_lowercase()
+    ))
+}
+
+// Created on first use and shared by every fetch, building a runtime per call spawns a new thread pool each time
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("Failed to start the tokio runtime"))
 }
 
 pub struct ExcitationFetcher {
-    pub excitation_levels: Arc<Mutex<Option<Vec<f64>>>>,
+    pub excitation_levels: Arc<Mutex<Option<Vec<Level>>>>,
     pub error_message: Arc<Mutex<Option<String>>>,
+    pub request_timeout: Duration,
+    // pause between isotopes in process_isotopes so NNDC isn't hammered
+    pub request_delay: Duration,
+    pub retry_delays_ms: Vec<u64>,
+    // NNDC dataset to read, empty for the adopted levels
+    pub dataset: String,
+    // decimals (MeV) the energies are rounded to, None keeps every digit NNDC gives
+    pub precision: Option<usize>,
 }
 
 impl ExcitationFetcher {
@@ -26,23 +162,31 @@ impl ExcitationFetcher {
         Self {
             excitation_levels: Arc::new(Mutex::new(None)),
             error_message: Arc::new(Mutex::new(None)),
+            request_timeout: Duration::from_secs(30),
+            request_delay: Duration::from_millis(500),
+            retry_delays_ms: RETRY_DELAYS_MS.to_vec(),
+            dataset: String::new(),
+            precision: Some(DEFAULT_PRECISION),
         }
     }
 
     pub fn fetch_excitation_levels(&self, isotope: &str) {
-        let rt = Runtime::new().unwrap();
+        // clear the previous isotope's result so a failure can't be read as its levels
+        *self.excitation_levels.lock().unwrap() = None;
+        *self.error_message.lock().unwrap() = None;
+
         let excitation_levels_clone = Arc::clone(&self.excitation_levels);
         let error_message_clone = Arc::clone(&self.error_message);
         let isotope = isotope.to_string();
 
         // Use the runtime to block on the async function
-        rt.block_on(async {
-            let result = self.get_excitations(&isotope).await;
+        runtime().block_on(async {
+            let result = self.get_excitations(&isotope, &self.dataset).await;
             match result {
                 Ok(levels) => {
                     let mut excitation_levels = excitation_levels_clone.lock().unwrap();
                     *excitation_levels = Some(levels);
-                },
+                }
                 Err(e) => {
                     let mut error_message = error_message_clone.lock().unwrap();
                     *error_message = Some(e.to_string());
@@ -51,83 +195,611 @@ impl ExcitationFetcher {
         });
     }
 
-    pub async fn get_excitations(&self, isotope: &str) -> Result<Vec<f64>, Box<dyn Error>> {
-        // Asynchronously fetch the webpage content
-        let url = format!("https://www.nndc.bnl.gov/nudat3/getdatasetClassic.jsp?nucleus={}&unc=nds", isotope);
-        let site_content = reqwest::get(&url).await?.text().await?;
+    // Fetches several isotopes at once with at most `limit` requests in flight.
+    // `on_result` is called with the isotope's index as each request finishes.
+    pub fn fetch_many<F>(&self, isotopes: Vec<String>, limit: usize, on_result: F)
+    where
+        F: Fn(usize, Result<Vec<Level>, String>) + Send + Sync + 'static,
+    {
+        let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+        let on_result = Arc::new(on_result);
+        let request_timeout = self.request_timeout;
+        let retry_delays_ms: Arc<[u64]> = self.retry_delays_ms.clone().into();
+        let dataset: Arc<str> = self.dataset.clone().into();
+        let precision = self.precision;
 
-        // Parse the HTML document
-        let document = Html::parse_document(&site_content);
-        let table_selector = Selector::parse("table").unwrap();
-
-        // Attempt to select the specific table
-        let tables = document.select(&table_selector).collect::<Vec<_>>();
-        if tables.len() < 3 {
-            return Err("Table not found or doesn't contain enough data".into());
-        }
-
-        // Prepare regex for cleaning and extracting numerical values
-        let re_clean = Regex::new(r"\s*(\d+(\.\d+)?(E[+\-]?\d+)?)\s*")?;
-
-        // Initialize a vector to hold the energy levels
-        let mut levels = Vec::new();
-
-        // Iterate over table rows, skipping the first header row
-        for row in tables[2].select(&Selector::parse("tr").unwrap()).skip(1) {
-            let entries = row.select(&Selector::parse("td").unwrap()).collect::<Vec<_>>();
-            if !entries.is_empty() {
-                let entry = &entries[0];
-                let text = entry.text().collect::<Vec<_>>().join("");
-                if let Some(caps) = re_clean.captures(&text) {
-                    if let Some(matched) = caps.get(1) {
-                        let cleaned_text = matched.as_str();
-                        match cleaned_text.parse::<f64>() {
-                            Ok(num) => {
-                                // Convert to MeV and format to 3 decimal places
-                                let formatted_num = format!("{:.3}", num / 1000.0);
-                                match formatted_num.parse::<f64>() {
-                                    Ok(formatted_num) => levels.push(formatted_num),
-                                    Err(_) => continue, // Skip entries that can't be formatted/parsed as f64
-                                }
-                            },
-                            Err(_) => continue, // Skip entries that can't be parsed as f64
-                        }
-                    }
-                }
+        runtime().block_on(async {
+            let mut tasks = JoinSet::new();
+            for (index, isotope) in isotopes.into_iter().enumerate() {
+                let semaphore = Arc::clone(&semaphore);
+                let on_result = Arc::clone(&on_result);
+                let retry_delays_ms = Arc::clone(&retry_delays_ms);
+                let dataset = Arc::clone(&dataset);
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    let result = fetch_levels(
+                        &isotope,
+                        &dataset,
+                        precision,
+                        request_timeout,
+                        &retry_delays_ms,
+                    )
+                    .await
+                    .map_err(|e| e.to_string());
+                    on_result(index, result);
+                });
             }
-        }
+            while tasks.join_next().await.is_some() {}
+        });
+    }
 
-        Ok(levels)
+    // `dataset` names an NNDC evaluation such as "12C(d,p)", empty reads the adopted levels
+    pub async fn get_excitations(
+        &self,
+        isotope: &str,
+        dataset: &str,
+    ) -> Result<Vec<Level>, Box<dyn Error + Send + Sync>> {
+        fetch_levels(
+            isotope,
+            dataset,
+            self.precision,
+            self.request_timeout,
+            &self.retry_delays_ms,
+        )
+        .await
     }
 
-    pub fn process_isotopes(&self, isotopes: &[Isotope]) -> Result<(), Box<dyn Error>> {
+    // Appends to excitation_levels.csv, skipping isotopes already in it unless `force` starts the file over
+    pub fn process_isotopes(
+        &self,
+        isotopes: &[Isotope],
+        force: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let done = if force {
+            HashSet::new()
+        } else {
+            processed_isotopes(OUTPUT_FILE)
+        };
+        let isotopes = isotopes
+            .iter()
+            .filter(|isotope| nndc_name(isotope).map_or(true, |name| !done.contains(&name)))
+            .collect::<Vec<_>>();
+        if !done.is_empty() {
+            println!(
+                "Resuming, {} isotopes already in {}",
+                done.len(),
+                OUTPUT_FILE
+            );
+        }
+
         let bar = ProgressBar::new(isotopes.len() as u64);
-        bar.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
-            .progress_chars("#>-"));
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+                )
+                .progress_chars("#>-"),
+        );
 
         let file = OpenOptions::new()
             .write(true)
-            .append(true)
+            .append(!force)
+            .truncate(force)
             .create(true)
-            .open("excitation_levels.csv")?;
+            .open(OUTPUT_FILE)?;
+        let needs_header = file.metadata()?.len() == 0;
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(file);
+        if needs_header {
+            writer.write_record(["isotope", "levels"])?;
+        }
+
+        let mut succeeded = 0;
+        let mut failed = Vec::new();
 
         for isotope in isotopes {
-            let isotope_name = format!("{}{}", isotope.a, isotope.el);
+            bar.inc(1);
+
+            let Some(isotope_name) = nndc_name(isotope) else {
+                bar.println(format!(
+                    "Skipping {}{}, NNDC has no level scheme for it",
+                    isotope.a, isotope.el
+                ));
+                continue;
+            };
+            if bar.position() > 1 {
+                std::thread::sleep(self.request_delay);
+            }
             self.fetch_excitation_levels(&isotope_name);
-            
+
+            // failed lookups are reported rather than written as empty rows
+            if let Some(error) = self.error_message.lock().unwrap().clone() {
+                bar.println(format!("No table for {}: {}", isotope_name, error));
+                failed.push(isotope_name);
+                continue;
+            }
+
             let excitation_levels = self.excitation_levels.lock().unwrap();
             let levels = excitation_levels.clone().unwrap_or_default();
-            let levels_str = levels.iter().map(|l| l.to_string()).collect::<Vec<_>>().join(", ");
-            
-            writeln!(&file, "{},[{}]", isotope_name, levels_str)?;
-
-            bar.inc(1);
+            write_levels_record(&mut writer, &isotope_name, &levels)?;
+            succeeded += 1;
         }
-        
+
         bar.finish_with_message("Done");
-        
+
+        println!("{} isotopes succeeded, {} failed", succeeded, failed.len());
+        if !failed.is_empty() {
+            println!("Failed: {}", failed.join(", "));
+        }
+
         Ok(())
     }
 }
 
+// Retries with backoff, NNDC intermittently returns errors or partial pages
+async fn fetch_levels(
+    isotope: &str,
+    dataset: &str,
+    precision: Option<usize>,
+    request_timeout: Duration,
+    retry_delays_ms: &[u64],
+) -> Result<Vec<Level>, Box<dyn Error + Send + Sync>> {
+    let mut attempt = 0;
+    loop {
+        match try_fetch_levels(isotope, dataset, precision, request_timeout).await {
+            Ok(levels) => return Ok(levels),
+            Err(e) if attempt < retry_delays_ms.len() => {
+                // a 429 asks us to wait at least Retry-After
+                let retry_after = e
+                    .downcast_ref::<RateLimited>()
+                    .and_then(|limited| limited.retry_after)
+                    .map_or(0, |retry_after| retry_after.as_millis() as u64);
+                let delay = retry_delays_ms[attempt].max(retry_after);
+                attempt += 1;
+                log::warn!(
+                    "Fetching {} failed: {}. Retrying in {} ms (retry {}/{})",
+                    isotope,
+                    e,
+                    delay,
+                    attempt,
+                    retry_delays_ms.len()
+                );
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// NuDat classic page for the isotope's adopted levels, or for one dataset when `dataset` is given
+pub fn dataset_url(
+    isotope: &str,
+    dataset: &str,
+) -> Result<reqwest::Url, Box<dyn Error + Send + Sync>> {
+    let mut params = vec![("nucleus", isotope), ("unc", "nds")];
+    if !dataset.trim().is_empty() {
+        params.push(("dsid", dataset.trim()));
+    }
+    Ok(reqwest::Url::parse_with_params(
+        "https://www.nndc.bnl.gov/nudat3/getdatasetClassic.jsp",
+        &params,
+    )?)
+}
+
+async fn try_fetch_levels(
+    isotope: &str,
+    dataset: &str,
+    precision: Option<usize>,
+    request_timeout: Duration,
+) -> Result<Vec<Level>, Box<dyn Error + Send + Sync>> {
+    // Asynchronously fetch the webpage content
+    let url = dataset_url(isotope, dataset)?;
+    let client = reqwest::Client::builder()
+        .timeout(request_timeout)
+        .build()?;
+    let response = client.get(url).send().await?;
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return Err(Box::new(RateLimited { retry_after }));
+    }
+    let site_content = response.error_for_status()?.text().await?;
+
+    parse_levels(&site_content, precision)
+}
+
+// Entities NNDC sometimes double escapes, so they survive HTML parsing as text
+const ENTITIES: [(&str, &str); 7] = [
+    ("&nbsp;", " "),
+    ("&#160;", " "),
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&plusmn;", "±"),
+    ("&asymp;", "≈"),
+    ("&amp;", "&"),
+];
+
+// Decodes leftover entities, turns non-breaking and thin spaces into plain ones, drops zero width
+// characters and collapses runs of whitespace
+fn normalize_cell_text(text: &str) -> String {
+    let mut text = text.to_string();
+    for (entity, decoded) in ENTITIES {
+        text = text.replace(entity, decoded);
+    }
+    text.replace(['\u{a0}', '\u{2009}', '\u{202f}'], " ")
+        .replace(['\u{200b}', '\u{feff}'], "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Text of a table cell, footnote markers in <sup> are left out unless `superscripts` is set
+// (half-lives need them for exponents like 7.17×10<sup>5</sup>)
+fn cell_text(cell: &ElementRef, superscripts: bool) -> String {
+    let text = cell
+        .descendants()
+        .filter(|node| {
+            superscripts
+                || !node
+                    .ancestors()
+                    .take_while(|ancestor| ancestor.id() != cell.id())
+                    .any(|ancestor| {
+                        ancestor
+                            .value()
+                            .as_element()
+                            .map_or(false, |element| element.name() == "sup")
+                    })
+        })
+        .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+        .collect::<String>();
+    normalize_cell_text(&text)
+}
+
+// Reads the levels out of a NuDat classic dataset page, rounded to `precision` decimals (MeV) when given
+pub fn parse_levels(
+    site_content: &str,
+    precision: Option<usize>,
+) -> Result<Vec<Level>, Box<dyn Error + Send + Sync>> {
+    // Parse the HTML document
+    let document = Html::parse_document(site_content);
+    let table_selector = Selector::parse("table").unwrap();
+
+    let tables = document.select(&table_selector).collect::<Vec<_>>();
+    let levels_table =
+        find_levels_table(&tables).ok_or("Table not found or doesn't contain enough data")?;
+
+    // Prepare regex for cleaning and extracting numerical values
+    let re_clean = Regex::new(r"\s*(\d+(\.\d+)?(E[+\-]?\d+)?)\s*")?;
+
+    // T1/2 usually follows Jπ, but look it up by its header in case NNDC adds columns
+    let half_life_column = levels_table
+        .select(&Selector::parse("tr").unwrap())
+        .next()
+        .and_then(|header| {
+            header
+                .select(&Selector::parse("th, td").unwrap())
+                .position(|cell| cell.text().collect::<String>().contains("T1/2"))
+        })
+        .unwrap_or(HALF_LIFE_COLUMN);
+
+    // Initialize a vector to hold the energy levels
+    let mut levels = Vec::new();
+
+    // Iterate over table rows, header rows are all <th> and some pages have more than one
+    for row in levels_table.select(&Selector::parse("tr").unwrap()) {
+        let entries = row
+            .select(&Selector::parse("td").unwrap())
+            .collect::<Vec<_>>();
+        if !entries.is_empty() {
+            let text = cell_text(&entries[0], false);
+            // Jπ is the second column, tentative assignments keep their brackets
+            let jpi = entries
+                .get(1)
+                .map(|jpi| cell_text(jpi, false))
+                .unwrap_or_default();
+            let half_life = entries
+                .get(half_life_column)
+                .map(|half_life| cell_text(half_life, true))
+                .unwrap_or_default();
+            if let Some(caps) = re_clean.captures(&text) {
+                if let Some(matched) = caps.get(1) {
+                    let cleaned_text = matched.as_str();
+                    let uncertainty =
+                        parse_uncertainty(cleaned_text, &text[caps.get(0).unwrap().end()..]);
+                    match cleaned_text.parse::<f64>() {
+                        Ok(num) => {
+                            // Convert to MeV, rounding through the formatter keeps e.g. 3.089 exact
+                            let energy = match precision {
+                                Some(decimals) => {
+                                    format!("{:.*}", decimals, num / 1000.0).parse::<f64>()
+                                }
+                                None => Ok(num / 1000.0),
+                            };
+                            match energy {
+                                Ok(energy) => levels.push(Level {
+                                    energy,
+                                    uncertainty,
+                                    jpi,
+                                    half_life,
+                                }),
+                                Err(_) => continue, // Skip entries that can't be formatted/parsed as f64
+                            }
+                        }
+                        Err(_) => continue, // Skip entries that can't be parsed as f64
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(with_ground_state(dedup_levels(levels)))
+}
+
+// The ground state is almost always wanted for calibration, put it back when the table lost it.
+// An empty table stays empty, it's an isotope without known levels rather than a ground state.
+fn with_ground_state(mut levels: Vec<Level>) -> Vec<Level> {
+    if levels.first().map_or(false, |level| level.energy != 0.0) {
+        levels.insert(
+            0,
+            Level {
+                energy: 0.0,
+                uncertainty: None,
+                jpi: String::new(),
+                half_life: String::new(),
+            },
+        );
+    }
+    levels
+}
+
+// Where the levels table has been on the page, used when no header matches
+const LEVELS_TABLE_INDEX: usize = 2;
+
+// Column of the T1/2 in the levels table, used when no header matches
+const HALF_LIFE_COLUMN: usize = 2;
+
+// The levels table is the one headed by an "E(level)" column. Layout tables wrapping it match too,
+// so the innermost match wins. Falls back to the historical index if NNDC renames the column.
+fn find_levels_table<'a>(tables: &[ElementRef<'a>]) -> Option<ElementRef<'a>> {
+    let row_selector = Selector::parse("tr").unwrap();
+    let matches = tables
+        .iter()
+        .filter(|table| {
+            table.select(&row_selector).next().map_or(false, |header| {
+                header.text().collect::<String>().contains("E(level)")
+            })
+        })
+        .collect::<Vec<_>>();
+
+    matches
+        .iter()
+        .find(|table| {
+            !matches.iter().any(|other| {
+                other.id() != table.id() && other.ancestors().any(|node| node.id() == table.id())
+            })
+        })
+        .map(|table| **table)
+        .or_else(|| tables.get(LEVELS_TABLE_INDEX).copied())
+}
+
+// Levels closer than this (MeV) are treated as the same state
+const DUPLICATE_TOLERANCE: f64 = 0.0005;
+
+// NNDC can list a level once per decay mode, rounding makes exact repeats, keep one of each
+fn dedup_levels(mut levels: Vec<Level>) -> Vec<Level> {
+    levels.sort_by(|a, b| a.energy.total_cmp(&b.energy));
+
+    let mut unique: Vec<Level> = Vec::with_capacity(levels.len());
+    for level in levels {
+        match unique.last_mut() {
+            Some(last) if level.energy - last.energy <= DUPLICATE_TOLERANCE => {
+                // keep whichever copy has the spin-parity and uncertainty
+                if last.jpi.is_empty() {
+                    last.jpi = level.jpi;
+                }
+                if last.half_life.is_empty() {
+                    last.half_life = level.half_life;
+                }
+                last.uncertainty = last.uncertainty.or(level.uncertainty);
+            }
+            _ => unique.push(level),
+        }
+    }
+
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_csv_round_trips() {
+        let path = std::env::temp_dir().join("nndc_levels_round_trip.csv");
+        let path = path.to_str().unwrap();
+
+        let level = |energy| Level {
+            energy,
+            uncertainty: None,
+            jpi: String::new(),
+            half_life: String::new(),
+        };
+        let mut writer = csv::Writer::from_path(path).unwrap();
+        writer.write_record(["isotope", "levels"]).unwrap();
+        write_levels_record(
+            &mut writer,
+            "12C",
+            &[level(0.0), level(4.439), level(7.654)],
+        )
+        .unwrap();
+        write_levels_record(&mut writer, "1H", &[]).unwrap();
+        drop(writer);
+
+        let rows = read_levels_csv(path).unwrap();
+        std::fs::remove_file(path).ok();
+
+        assert_eq!(
+            rows,
+            vec![
+                ("12C".to_string(), vec![0.0, 4.439, 7.654]),
+                ("1H".to_string(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_levels_are_merged_and_sorted() {
+        let row = |energy: &str, jpi: &str| format!("<tr><td>{}</td><td>{}</td></tr>", energy, jpi);
+        let html = format!(
+            "<html><body><table></table><table></table><table><tr><th>E(level)</th><th>Jπ</th></tr>{}{}{}{}{}</table></body></html>",
+            row("3089.443 20", "1/2+"),
+            row("0.0", "1/2-"),
+            row("3089.4 3", ""),
+            row("3684.507 19", "3/2-"),
+            row("0", ""),
+        );
+
+        let levels = parse_levels(&html, Some(DEFAULT_PRECISION)).unwrap();
+        let energies = levels.iter().map(|level| level.energy).collect::<Vec<_>>();
+
+        assert_eq!(energies, vec![0.0, 3.089, 3.685]);
+        assert_eq!(levels[0].jpi, "1/2-");
+        assert_eq!(levels[1].jpi, "1/2+");
+    }
+
+    #[test]
+    fn precision_sets_the_rounding() {
+        let html = "<html><body><table>\
+            <tr><th>E(level)(keV)</th><th>Jπ(level)</th></tr>\
+            <tr><td>3089.443 20</td><td>1/2+</td></tr>\
+            </table></body></html>";
+        let energy = |precision| parse_levels(html, precision).unwrap()[1].energy;
+
+        assert_eq!(energy(None), 3.089443);
+        assert_eq!(energy(Some(5)), 3.08944);
+        assert_eq!(energy(Some(1)), 3.1);
+    }
+
+    #[test]
+    fn levels_table_is_found_by_its_header() {
+        let html = "<html><body>\
+            <table><tr><td>Search</td></tr></table>\
+            <table><tr><td><table><tr><th>E(level)(keV)</th><th>Jπ(level)</th></tr>\
+            <tr><td>0.0</td><td>0+</td></tr><tr><td>4438.91 31</td><td>2+</td></tr></table></td></tr></table>\
+            <table><tr><th>Reference</th></tr><tr><td>1234</td></tr></table>\
+            </body></html>";
+
+        let levels = parse_levels(html, Some(DEFAULT_PRECISION)).unwrap();
+        let energies = levels.iter().map(|level| level.energy).collect::<Vec<_>>();
+
+        assert_eq!(energies, vec![0.0, 4.439]);
+        assert_eq!(levels[1].jpi, "2+");
+    }
+
+    #[test]
+    fn half_lives_are_read_from_their_column() {
+        let html = "<html><body><table>\
+            <tr><th>E(level)(keV)</th><th>Jπ(level)</th><th>T1/2(level)</th></tr>\
+            <tr><td>0.0</td><td>5+</td><td>7.17×10<sup>5</sup> y 24</td></tr>\
+            <tr><td>228.305 13</td><td>0+</td><td>6.3460 s 6</td></tr>\
+            <tr><td>416.852 20</td><td>3+</td><td>1.25 ns 3</td></tr>\
+            </table></body></html>";
+
+        let levels = parse_levels(html, Some(DEFAULT_PRECISION)).unwrap();
+        let half_lives = levels
+            .iter()
+            .map(|level| level.half_life.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(half_lives, vec!["7.17×105 y 24", "6.3460 s 6", "1.25 ns 3"]);
+    }
+
+    #[test]
+    fn noisy_cells_are_normalized_before_parsing() {
+        let html = "<html><body><table>\
+            <tr><th>E(level)(keV)</th><th>Jπ(level)</th><th>T1/2(level)</th></tr>\
+            <tr><td>&nbsp;0.0&nbsp;</td><td>1/2-</td><td>STABLE</td></tr>\
+            <tr><td>&amp;#160;3089.443&amp;nbsp;20</td><td>1/2+&nbsp;</td><td>1.07&amp;nbsp;fs 5</td></tr>\
+            <tr><td><sup>@</sup>3684.507 19</td><td>3/2-<sup>b</sup></td><td></td></tr>\
+            <tr><td>3853.807<sup>1</sup> 19</td><td>(5/2)+</td><td></td></tr>\
+            <tr><td>\u{200b}6864\u{2009}5</td><td>5/2+</td><td></td></tr>\
+            </table></body></html>";
+
+        let levels = parse_levels(html, Some(6)).unwrap();
+        let energies = levels.iter().map(|level| level.energy).collect::<Vec<_>>();
+
+        assert_eq!(energies, vec![0.0, 3.089443, 3.684507, 3.853807, 6.864]);
+        for (index, uncertainty) in [(1, 0.00002), (3, 0.000019), (4, 0.005)] {
+            assert!((levels[index].uncertainty.unwrap() - uncertainty).abs() < 1e-12);
+        }
+        assert_eq!(levels[1].jpi, "1/2+");
+        assert_eq!(levels[2].jpi, "3/2-");
+        assert_eq!(levels[1].half_life, "1.07 fs 5");
+    }
+
+    #[test]
+    fn cell_text_keeps_only_plain_spaces() {
+        assert_eq!(normalize_cell_text(" 1368.672\u{a0}\u{a0}5 "), "1368.672 5");
+        assert_eq!(normalize_cell_text("&lt;1.5&nbsp;ps"), "<1.5 ps");
+        assert_eq!(normalize_cell_text("&amp;amp;"), "&amp;");
+    }
+
+    #[test]
+    fn ground_state_is_returned_with_the_levels() {
+        // two header rows, the second one spelled with <td>, then the ground state
+        let html = "<html><body><table>\
+            <tr><th>E(level)(keV)</th><th>Jπ(level)</th></tr>\
+            <tr><td>E(level)</td><td>Jπ</td></tr>\
+            <tr><td>0.0</td><td>0+</td></tr>\
+            <tr><td>1779.030 11</td><td>2+</td></tr>\
+            </table></body></html>";
+        let levels = parse_levels(html, Some(DEFAULT_PRECISION)).unwrap();
+        let energies = levels.iter().map(|level| level.energy).collect::<Vec<_>>();
+        assert_eq!(energies, vec![0.0, 1.779]);
+        assert_eq!(levels[0].jpi, "0+");
+
+        // a dataset page starting above the ground state still gets one
+        let html = "<html><body><table>\
+            <tr><th>E(level)(keV)</th><th>Jπ(level)</th></tr>\
+            <tr><td>1779.030 11</td><td>2+</td></tr>\
+            </table></body></html>";
+        let levels = parse_levels(html, Some(DEFAULT_PRECISION)).unwrap();
+        let energies = levels.iter().map(|level| level.energy).collect::<Vec<_>>();
+        assert_eq!(energies, vec![0.0, 1.779]);
+        assert!(levels[0].jpi.is_empty());
+
+        // but a table without levels doesn't get one made up
+        let html = "<html><body><table>\
+            <tr><th>E(level)(keV)</th><th>Jπ(level)</th></tr>\
+            <tr><td>E(level)</td><td>Jπ</td></tr>\
+            </table></body></html>";
+        assert!(parse_levels(html, Some(DEFAULT_PRECISION))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn dataset_is_added_to_the_url() {
+        assert_eq!(
+            dataset_url("13C", "").unwrap().as_str(),
+            "https://www.nndc.bnl.gov/nudat3/getdatasetClassic.jsp?nucleus=13C&unc=nds"
+        );
+        assert_eq!(
+            dataset_url("13C", "12C(d,p)").unwrap().as_str(),
+            "https://www.nndc.bnl.gov/nudat3/getdatasetClassic.jsp?nucleus=13C&unc=nds&dsid=12C%28d%2Cp%29"
+        );
+    }
+
+    #[test]
+    fn repeated_fetches_reuse_the_runtime() {
+        let first: *const Runtime = runtime();
+        let handle = std::thread::spawn(|| runtime() as *const Runtime as usize);
+        assert_eq!(handle.join().unwrap(), first as usize);
+        assert!(std::ptr::eq(first, runtime()));
+    }
+}
@@ -1,2 +1,2 @@
 pub mod excitation_fetcher;
-pub mod nuclear_data_amdc_2016;
\ No newline at end of file
+pub mod nuclear_data_amdc_2016;
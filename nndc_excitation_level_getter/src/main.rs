@@ -1,9 +1,48 @@
+use nndc_excitation_level_getter::excitation_fetcher::{ExcitationFetcher, DEFAULT_PRECISION};
 use nndc_excitation_level_getter::nuclear_data_amdc_2016::ISOTOPES;
-use nndc_excitation_level_getter::excitation_fetcher::ExcitationFetcher;
+use std::time::Duration;
 
 fn main() {
-    let fetcher = ExcitationFetcher::new();
-    match fetcher.process_isotopes(&ISOTOPES) {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    // --force starts excitation_levels.csv over instead of resuming it
+    let force = args.iter().any(|arg| arg == "--force");
+
+    let mut fetcher = ExcitationFetcher::new();
+
+    // --delay-ms <ms> pauses between requests, keep it polite when rebuilding the whole table
+    if let Some(index) = args.iter().position(|arg| arg == "--delay-ms") {
+        match args.get(index + 1).map(|value| value.parse::<u64>()) {
+            Some(Ok(delay_ms)) => fetcher.request_delay = Duration::from_millis(delay_ms),
+            _ => {
+                eprintln!("--delay-ms expects a number of milliseconds");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // --precision <decimals|full> rounds the energies (MeV) written to the CSV, keV by default
+    if let Some(index) = args.iter().position(|arg| arg == "--precision") {
+        fetcher.precision = match args.get(index + 1).map(String::as_str) {
+            Some("full") => None,
+            Some(decimals) => match decimals.parse::<usize>() {
+                Ok(decimals) => Some(decimals),
+                Err(_) => {
+                    eprintln!(
+                        "--precision expects a number of decimals or \"full\", the default is {}",
+                        DEFAULT_PRECISION
+                    );
+                    std::process::exit(1);
+                }
+            },
+            None => {
+                eprintln!("--precision expects a number of decimals or \"full\"");
+                std::process::exit(1);
+            }
+        };
+    }
+
+    match fetcher.process_isotopes(&ISOTOPES, force) {
         Ok(_) => println!("Excitation levels saved to CSV successfully."),
         Err(e) => eprintln!("Error processing isotopes: {}", e),
     }
@@ -26,8 +26,9 @@ const fn new_isotope(
     }
 }
 
-// /* 
+// /*
 // from the amdc_2016 evaluation
+#[rustfmt::skip]
 pub const ISOTOPES: [Isotope; 2498] = [
     new_isotope(1, 0, 1, "n", 1, 008664.91582),
     new_isotope(0, 1, 1, "H", 1, 007825.03224),
@@ -2528,4 +2529,4 @@ new_isotope(58, 108, 266, "Hs", 266, 130045.252),
 new_isotope(59, 110, 269, "Ds", 269, 144751.021),
 new_isotope(60, 110, 270, "Ds", 270, 144583.090),
 ];
-// */
\ No newline at end of file
+// */
@@ -3,18 +3,435 @@ use log::info;
 use eframe::egui::{self, Color32, Stroke};
 use eframe::App;
 use egui_extras::{Column, TableBuilder};
-use egui_plot::{Bar, BarChart, Legend, Orientation, Plot, PlotBounds, VLine};
+use egui_plot::{
+    Bar, BarChart, GridMark, HLine, Legend, Line, LineStyle, Orientation, Plot, PlotBounds,
+    PlotMemory, PlotPoints, Polygon, Text, VLine,
+};
 
-use std::collections::HashMap;
-use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
 
-use super::excitation_levels_nndc::ExcitationLevels;
-use super::nuclear_data_amdc_2016::NuclearData;
+use super::excitation_fetcher::{now_utc, DataSource};
+use super::excitation_levels_nndc::{nndc_url, Level, BUNDLED_LEVELS_RETRIEVED};
+use super::kinematics::{
+    brho_in, momentum_acceptance, FieldUnit, ReactionNuclei, RhoUnit, QBRHO2P,
+};
+use super::nuclear_data_amdc_2016::{parse_isotope, MassEvaluation, MassSource, NuclearData};
+use super::spectrum::{fit_polynomial, subtract_background};
 
-const C: f64 = 299792458.0; // Speed of light in m/s
-const QBRHO2P: f64 = 1.0E-9 * C; // Converts qbrho to momentum (p) (kG*cm -> MeV/c)
+// largest angle the spectrograph can be rotated to (degree), change for other instruments
+const MAX_SPS_ANGLE: f64 = 60.0;
+
+// strongest field the dipole reaches (kG)
+const MAX_FIELD: f64 = 17.0;
+
+// height of a reaction's level list in rows, longer lists scroll
+const LEVEL_ROWS_SHOWN: usize = 25;
+
+// (Z, A) of the usual target contaminants, carbon and oxygen buildup and hydrogen
+const CONTAMINANT_TARGETS: [(i32, i32); 4] = [(6, 12), (8, 16), (1, 1), (1, 2)];
+
+// (name, Z, A) of the beams and ejectiles almost every SE-SPS reaction uses
+const LIGHT_IONS: [(&str, i32, i32); 5] = [
+    ("p", 1, 1),
+    ("d", 1, 2),
+    ("t", 1, 3),
+    ("3He", 2, 3),
+    ("α", 2, 4),
+];
+
+// (name, target, projectile, ejectile) with each nucleus given as (Z, A)
+type ReactionPreset = (&'static str, (i32, i32), (i32, i32), (i32, i32));
+
+const REACTION_PRESETS: [ReactionPreset; 6] = [
+    ("12C(d,p)13C", (6, 12), (1, 2), (1, 1)),
+    ("27Al(d,p)28Al", (13, 27), (1, 2), (1, 1)),
+    ("40Ca(3He,d)41Sc", (20, 40), (2, 3), (1, 2)),
+    ("28Si(p,t)26Si", (14, 28), (1, 1), (1, 3)),
+    ("24Mg(d,3He)23Na", (12, 24), (1, 2), (2, 3)),
+    ("208Pb(d,p)209Pb", (82, 208), (1, 2), (1, 1)),
+];
+
+const REACTION_COLORS: [Color32; 15] = [
+    Color32::from_rgb(120, 47, 64), // go noles!
+    Color32::from_rgb(206, 184, 136),
+    Color32::BLUE,
+    Color32::GREEN,
+    Color32::YELLOW,
+    Color32::BROWN,
+    Color32::DARK_RED,
+    Color32::RED,
+    Color32::LIGHT_RED,
+    Color32::LIGHT_YELLOW,
+    Color32::KHAKI,
+    Color32::DARK_GREEN,
+    Color32::LIGHT_GREEN,
+    Color32::DARK_BLUE,
+    Color32::LIGHT_BLUE,
+];
+
+const VIRIDIS_COLORS: [Color32; 8] = [
+    Color32::from_rgb(68, 1, 84),
+    Color32::from_rgb(70, 50, 126),
+    Color32::from_rgb(54, 92, 141),
+    Color32::from_rgb(39, 127, 142),
+    Color32::from_rgb(31, 161, 135),
+    Color32::from_rgb(74, 193, 109),
+    Color32::from_rgb(160, 218, 57),
+    Color32::from_rgb(253, 231, 37),
+];
+
+// colorblind safe, black moved to the end so it isn't lost on the dark theme
+const OKABE_ITO_COLORS: [Color32; 8] = [
+    Color32::from_rgb(230, 159, 0),
+    Color32::from_rgb(86, 180, 233),
+    Color32::from_rgb(0, 158, 115),
+    Color32::from_rgb(240, 228, 66),
+    Color32::from_rgb(0, 114, 178),
+    Color32::from_rgb(213, 94, 0),
+    Color32::from_rgb(204, 121, 167),
+    Color32::BLACK,
+];
+
+// categorical palette for coloring levels by spin, indexed by 2J (matplotlib's tab10)
+const SPIN_COLORS: [Color32; 10] = [
+    Color32::from_rgb(31, 119, 180),
+    Color32::from_rgb(255, 127, 14),
+    Color32::from_rgb(44, 160, 44),
+    Color32::from_rgb(214, 39, 40),
+    Color32::from_rgb(148, 103, 189),
+    Color32::from_rgb(140, 86, 75),
+    Color32::from_rgb(227, 119, 194),
+    Color32::from_rgb(127, 127, 127),
+    Color32::from_rgb(188, 189, 34),
+    Color32::from_rgb(23, 190, 207),
+];
+
+fn spin_color(two_j: u32) -> Color32 {
+    SPIN_COLORS[two_j as usize % SPIN_COLORS.len()]
+}
+
+fn spin_label(two_j: u32) -> String {
+    if two_j % 2 == 0 {
+        (two_j / 2).to_string()
+    } else {
+        format!("{}/2", two_j)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PlotOrientation {
+    // earlier projects named them after the bars
+    #[default]
+    #[serde(alias = "Vertical")]
+    RhoAlongX, // reactions stacked upwards
+    #[serde(alias = "Horizontal")]
+    RhoAlongY, // reactions stacked to the right
+}
+
+impl PlotOrientation {
+    // maps a (rho, stacking offset) pair to plot coordinates
+    pub fn point(&self, rho: f64, offset: f64) -> [f64; 2] {
+        match self {
+            PlotOrientation::RhoAlongX => [rho, offset],
+            PlotOrientation::RhoAlongY => [offset, rho],
+        }
+    }
+
+    // index of the stacking offset in plot coordinates
+    fn offset_axis(&self) -> usize {
+        match self {
+            PlotOrientation::RhoAlongX => 1,
+            PlotOrientation::RhoAlongY => 0,
+        }
+    }
+
+    fn bar_orientation(&self) -> Orientation {
+        match self {
+            PlotOrientation::RhoAlongX => Orientation::Vertical,
+            PlotOrientation::RhoAlongY => Orientation::Horizontal,
+        }
+    }
+}
+
+// column the results table is sorted by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum ResultsColumn {
+    #[default]
+    Reaction,
+    Energy,
+    Jpi,
+    Rho,
+    Brho,
+    Acceptance,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PlotMode {
+    #[default]
+    FocalPlane, // every reaction's levels at the current settings
+    AngleScan,      // rho of one level as the spectrograph angle is swept
+    BeamEnergyScan, // rho of one level as the beam energy is swept
+}
+
+// Outline of a reaction's bars, so reactions can be told apart without color
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum BarStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl BarStyle {
+    pub const ALL: [BarStyle; 3] = [BarStyle::Solid, BarStyle::Dashed, BarStyle::Dotted];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BarStyle::Solid => "Solid",
+            BarStyle::Dashed => "Dashed",
+            BarStyle::Dotted => "Dotted",
+        }
+    }
+
+    fn line_style(&self) -> LineStyle {
+        match self {
+            BarStyle::Solid => LineStyle::Solid,
+            BarStyle::Dashed => LineStyle::dashed_dense(),
+            BarStyle::Dotted => LineStyle::dotted_dense(),
+        }
+    }
+
+    // stroke-dasharray for the SVG export
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn dash_array(&self) -> Option<&'static str> {
+        match self {
+            BarStyle::Solid => None,
+            BarStyle::Dashed => Some("6 3"),
+            BarStyle::Dotted => Some("1.5 3"),
+        }
+    }
+}
+
+// How levels that miss the focal plane are drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum OutsideAcceptance {
+    #[default]
+    Show,
+    Fade,
+    Hide,
+}
+
+impl OutsideAcceptance {
+    pub const ALL: [OutsideAcceptance; 3] = [
+        OutsideAcceptance::Show,
+        OutsideAcceptance::Fade,
+        OutsideAcceptance::Hide,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutsideAcceptance::Show => "Show",
+            OutsideAcceptance::Fade => "Fade",
+            OutsideAcceptance::Hide => "Hide",
+        }
+    }
+}
+
+// Colors handed out to new reactions in turn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum ColorPalette {
+    #[default]
+    Garnet,
+    Tab10,
+    Viridis,
+    OkabeIto,
+    Custom,
+}
+
+impl ColorPalette {
+    pub const ALL: [ColorPalette; 5] = [
+        ColorPalette::Garnet,
+        ColorPalette::Tab10,
+        ColorPalette::Viridis,
+        ColorPalette::OkabeIto,
+        ColorPalette::Custom,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorPalette::Garnet => "Garnet & Gold",
+            ColorPalette::Tab10 => "tab10",
+            ColorPalette::Viridis => "Viridis",
+            ColorPalette::OkabeIto => "Okabe-Ito",
+            ColorPalette::Custom => "Custom",
+        }
+    }
+
+    // the custom palette's colors are kept in the app
+    fn colors(&self) -> &'static [Color32] {
+        match self {
+            ColorPalette::Garnet | ColorPalette::Custom => &REACTION_COLORS,
+            ColorPalette::Tab10 => &SPIN_COLORS,
+            ColorPalette::Viridis => &VIRIDIS_COLORS,
+            ColorPalette::OkabeIto => &OKABE_ITO_COLORS,
+        }
+    }
+}
+
+// grayed out and translucent, for levels that miss the detector
+fn faded(color: Color32) -> Color32 {
+    let [r, g, b, _] = color.to_array();
+    let gray = (r as u16 + g as u16 + b as u16) / 3;
+    let mix = |channel: u8| ((channel as u16 + gray) / 2) as u8;
+    Color32::from_rgb(mix(r), mix(g), mix(b)).gamma_multiply(0.35)
+}
+
+// Inputs for a rough counts estimate. The absolute numbers ignore efficiencies, charge states and
+// dead time, only the relative peak heights are meant to be trusted.
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct YieldSettings {
+    pub enabled: bool,
+    pub beam_current: f64,     // enA
+    pub target_thickness: f64, // μg/cm²
+    pub run_time: f64,         // hours
+    pub solid_angle: f64,      // msr
+    pub cross_section: f64,    // mb/sr, scaled by each level's relative cross section
+}
+
+impl Default for YieldSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            beam_current: 50.0,
+            target_thickness: 50.0,
+            run_time: 24.0,
+            solid_angle: 4.6, // SE-SPS maximum
+            cross_section: 1.0,
+        }
+    }
+}
+
+impl YieldSettings {
+    // counts = beam particles * target atoms per cm² * dσ/dΩ * ΔΩ, with a fully stripped beam
+    pub fn counts(&self, projectile_z: i32, target_a: i32, relative_cross_section: f64) -> f64 {
+        const ELEMENTARY_CHARGE: f64 = 1.602176634e-19; // C
+        const AVOGADRO: f64 = 6.02214076e23; // 1/mol
+
+        if projectile_z <= 0 || target_a <= 0 {
+            return 0.0;
+        }
+
+        let particles = self.beam_current * 1e-9 / (projectile_z as f64 * ELEMENTARY_CHARGE)
+            * self.run_time
+            * 3600.0;
+        let atoms = self.target_thickness * 1e-6 * AVOGADRO / target_a as f64;
+        let cross_section = self.cross_section * relative_cross_section * 1e-27; // cm²/sr
+
+        particles * atoms * cross_section * self.solid_angle * 1e-3
+    }
+}
+
+// Beam energy from a tandem's terminal voltage. Negative ions from the source gain 1 MeV per MV
+// up to the terminal, are stripped to `charge_state` and gain that many MeV per MV on the way down.
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TandemSettings {
+    pub enabled: bool,
+    pub terminal_voltage: f64, // MV
+    pub charge_state: u32,     // after the terminal stripper
+    pub injection_energy: f64, // keV, from the ion source
+}
+
+impl Default for TandemSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            terminal_voltage: 8.0,
+            charge_state: 1,
+            injection_energy: 0.0,
+        }
+    }
+}
+
+impl TandemSettings {
+    // MeV, E = E_inj + (1 + q) V
+    pub fn beam_energy(&self) -> f64 {
+        self.injection_energy / 1000.0 + (1.0 + self.charge_state as f64) * self.terminal_voltage
+    }
+}
+
+// settings the side panel shows every reaction's levels with
+pub struct LevelsUiOptions {
+    pub data_source: DataSource,
+    pub acceptance: (f64, f64), // rho (cm)
+    pub show_cross_sections: bool,
+    pub energy_decimals: usize,
+    pub rho_unit: RhoUnit,
+}
+
+// settings shared by every reaction drawn on the focal plane plot
+pub struct DrawOptions {
+    pub show_peak_labels: bool,
+    pub orientation: PlotOrientation,
+    pub acceptance: (f64, f64),            // rho (cm)
+    pub search_window: Option<(f64, f64)>, // rho (cm) range to highlight
+    pub bar_height: f64,
+    pub bar_width: f64, // cm
+    pub color_by_spin: bool,
+    pub rho_resolution: f64,      // cm, closer levels are drawn merged
+    pub magnetic_field: f64,      // kG the rho values were calculated at
+    pub field_settings: Vec<f64>, // other fields (kG) drawn faded for comparison
+    pub field_unit: FieldUnit,    // units for the hover text only, plot coordinates stay in cm
+    pub rho_unit: RhoUnit,
+    pub highlighted_excitation: Option<f64>, // level picked in the results table
+    pub outside_acceptance: OutsideAcceptance,
+    // bar heights follow the estimated counts, relative to the largest (counts) peak
+    pub yields: Option<(YieldSettings, f64)>,
+    pub ground_state_only: bool, // just the lowest level of each reaction, for beam tuning
+    pub energy_decimals: usize,  // display only, the energies keep their full precision
+    // legend entry of the reaction being drawn, see `legend_name`
+    pub legend_name: String,
+}
 
 #[derive(Clone, serde::Deserialize, serde::Serialize, Debug, Default)]
+#[serde(default)]
+pub struct RhoValue {
+    pub excitation: f64,
+    pub uncertainty: Option<f64>,
+    pub jpi: String,
+    pub rho: f64,
+    pub rho_uncertainty: Option<f64>,
+    pub ejectile_energy: f64, // MeV
+    pub momentum: f64,        // MeV/c
+    pub brho: f64,            // kG*cm
+    pub half_life: String,    // NNDC T1/2, empty when unknown
+    pub isomer: bool,
+    pub name: String, // of the additional level, empty for unnamed and fetched ones
+}
+
+impl RhoValue {
+    // Twice the spin of the first J in the Jπ string, so "(1/2,3/2)+" gives 1 and "2+" gives 4.
+    // Tentative and ambiguous assignments use their first value, None when no J is given.
+    pub fn two_j(&self) -> Option<u32> {
+        let start = self.jpi.find(|c: char| c.is_ascii_digit())?;
+        let spin: String = self.jpi[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '/')
+            .collect();
+
+        match spin.split_once('/') {
+            Some((numerator, "2")) => numerator.parse().ok(),
+            Some(_) => None,
+            None => spin.parse::<u32>().ok().map(|j| 2 * j),
+        }
+    }
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize, Debug)]
+#[serde(default)]
 pub struct Reaction {
     pub target_z: i32,
     pub target_a: i32,
@@ -31,16 +448,160 @@ pub struct Reaction {
     pub resid_z: i32,
     pub resid_a: i32,
     pub resid_data: Option<NuclearData>,
+    // residual Z/A are entered by hand instead of derived, for breakup or multi-nucleon channels
+    pub manual_residual: bool,
+    // the target nucleus is the beam and the projectile sits in the target foil, e.g. a 12C beam on
+    // CD2 for 12C(d,p)13C. The kinematics swap the two and the identifier reads d(12C,p)13C.
+    pub inverse_kinematics: bool,
+
+    // user supplied nuclear masses (MeV) used instead of the table
+    pub target_mass_override: Option<f64>,
+    pub projectile_mass_override: Option<f64>,
+    pub ejectile_mass_override: Option<f64>,
+    pub resid_mass_override: Option<f64>,
+
+    // typed names like "12C", resolved to Z/A on enter
+    pub target_name: String,
+    pub projectile_name: String,
+    pub ejectile_name: String,
 
     pub reaction_identifier: String,
+    // why the reaction is on the plot, e.g. "background from 16O buildup"
+    pub notes: String,
 
-    pub excitation_levels: Vec<f64>,
+    #[serde(deserialize_with = "saved_levels")]
+    pub excitation_levels: Vec<Level>,
+    // residual the levels were looked up for, so they can be reused after a restart
+    pub levels_isotope: String,
+    pub levels_source: String, // e.g. the NNDC snapshot date, for the About panel
+    // NNDC dataset to read the levels from, empty for the adopted levels
+    pub nndc_dataset: String,
+    // local ENSDF file read instead of the data source, None uses the data source
+    pub ensdf_file: Option<String>,
+    #[serde(skip)]
+    naming_dataset: bool, // "Named" picked but nothing typed yet
+    #[serde(skip)]
+    pub levels_fetched: bool, // false when the levels came from the saved session
+    #[serde(skip)]
+    pub levels_queued: bool, // waiting on a background NNDC fetch
+    // when the levels were last fetched from NNDC, or the retrieval date of the bundled ones.
+    // Empty for levels read from a file.
+    pub levels_fetched_at: String,
     pub add_excitation_level: f64,
-    pub additional_excitation_levels: Vec<f64>,
+    // (name, MeV), e.g. a predicted or tentative state, the name may be empty
+    #[serde(deserialize_with = "named_levels")]
+    pub additional_excitation_levels: Vec<(String, f64)>,
+    // fixed rho (cm) reference lines, e.g. known contaminant peaks
+    pub rho_markers: Vec<f64>,
+    pub add_as_rho_marker: bool,
+
+    // only fetched levels inside this window (MeV) are converted to rho
+    pub level_filter_min: f64,
+    pub level_filter_max: f64,
+    // energy (MeV) of the one isomer to plot, None plots every level
+    pub isomer: Option<f64>,
+
+    // (excitation MeV, cross section relative to the flat one) for the yield estimate, 1 when absent
+    pub cross_sections: Vec<(f64, f64)>,
+    // adopted levels of the ejectile, for particle-gamma coincidences or an unbound ejectile
+    pub ejectile_levels: Vec<Level>,
+    #[serde(skip)]
+    pub ejectile_levels_error: Option<String>,
+    #[serde(skip)]
+    pub ejectile_levels_queued: bool,
+    // excitations (MeV) the field and angle search tries to get onto the focal plane
+    pub must_see: Vec<f64>,
 
-    pub rho_values: Vec<(f64, f64)>,
+    #[serde(deserialize_with = "saved_rho_values")]
+    pub rho_values: Vec<RhoValue>,
+    // ground state threshold (MeV) from the last calculation
+    pub threshold: Option<f64>,
 
     pub color: Color32,
+    pub style: BarStyle,
+    pub hatch: bool, // bars are hatched instead of filled, for grayscale prints
+    // beam on target contaminants are drawn faded
+    pub contaminant: bool,
+    // made with the Copy button, so not flagged as a duplicate of its original
+    pub copy: bool,
+    // false when hidden from the plot, also toggled by its legend entry
+    pub visible: bool,
+    // index of the reaction this one follows from, e.g. the decay of a state it populates.
+    // Secondary reactions are listed and stacked under their parent.
+    pub parent: Option<usize>,
+
+    #[serde(skip)]
+    pub fetch_error: Option<String>,
+    #[serde(skip)]
+    pub import_message: Option<String>,
+    #[serde(skip)]
+    pub name_error: Option<String>,
+    #[serde(skip)]
+    pub calculation_error: Option<String>,
+}
+
+impl Default for Reaction {
+    fn default() -> Self {
+        Self {
+            target_z: 0,
+            target_a: 0,
+            target_data: None,
+            projectile_z: 0,
+            projectile_a: 0,
+            projectile_data: None,
+            ejectile_z: 0,
+            ejectile_a: 0,
+            ejectile_data: None,
+            resid_z: 0,
+            resid_a: 0,
+            resid_data: None,
+            manual_residual: false,
+            inverse_kinematics: false,
+            target_mass_override: None,
+            projectile_mass_override: None,
+            ejectile_mass_override: None,
+            resid_mass_override: None,
+            target_name: String::new(),
+            projectile_name: String::new(),
+            ejectile_name: String::new(),
+            reaction_identifier: String::new(),
+            notes: String::new(),
+            excitation_levels: Vec::new(),
+            levels_isotope: String::new(),
+            levels_source: String::new(),
+            nndc_dataset: String::new(),
+            ensdf_file: None,
+            naming_dataset: false,
+            levels_fetched: false,
+            levels_queued: false,
+            levels_fetched_at: String::new(),
+            add_excitation_level: 0.0,
+            additional_excitation_levels: Vec::new(),
+            rho_markers: Vec::new(),
+            add_as_rho_marker: false,
+            level_filter_min: 0.0,
+            level_filter_max: f64::MAX,
+            isomer: None,
+            must_see: Vec::new(),
+            cross_sections: Vec::new(),
+            ejectile_levels: Vec::new(),
+            ejectile_levels_error: None,
+            ejectile_levels_queued: false,
+            rho_values: Vec::new(),
+            threshold: None,
+            color: Color32::default(),
+            style: BarStyle::Solid,
+            hatch: false,
+            contaminant: false,
+            copy: false,
+            visible: true,
+            parent: None,
+            fetch_error: None,
+            import_message: None,
+            name_error: None,
+            calculation_error: None,
+        }
+    }
 }
 
 impl Reaction {
@@ -51,7 +612,19 @@ impl Reaction {
         }
     }
 
-    pub fn excitation_levels_ui(&mut self, ui: &mut egui::Ui, index: usize) {
+    pub fn excitation_levels_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        options: &LevelsUiOptions,
+    ) {
+        let LevelsUiOptions {
+            data_source,
+            acceptance,
+            show_cross_sections,
+            energy_decimals,
+            rho_unit,
+        } = *options;
         egui::ScrollArea::vertical()
             .id_source(format!("Reaction {} Scroll Area", index))
             .show(ui, |ui| {
@@ -61,52 +634,253 @@ impl Reaction {
                 ui.horizontal(|ui| {
                     ui.label("Color: ");
                     ui.color_edit_button_srgba(&mut self.color);
+                    egui::ComboBox::from_id_source(format!("Reaction {} Style", index))
+                        .selected_text(self.style.label())
+                        .show_ui(ui, |ui| {
+                            for style in BarStyle::ALL {
+                                ui.selectable_value(&mut self.style, style, style.label());
+                            }
+                        });
+                    ui.checkbox(&mut self.hatch, "Hatch");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Excitation Levels");
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui
+                        .button("Load levels from CSV")
+                        .on_hover_text("One energy (MeV) per line with an optional Jπ column")
+                        .clicked()
+                    {
+                        self.load_levels_from_csv();
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.ensdf_ui(ui, data_source);
                 });
-                ui.label("Excitation Levels");
+
+                if let Some(message) = &self.import_message {
+                    ui.label(message);
+                }
+
                 ui.separator();
 
+                if let Some(error) = self.fetch_error.clone() {
+                    let isotope = self
+                        .resid_data
+                        .as_ref()
+                        .map_or("None", |data| &data.isotope)
+                        .to_string();
+
+                    ui.colored_label(Color32::RED, format!("{}: {}", isotope, error));
+                    // the page of the dataset that failed, the adopted levels unless one was named
+                    let dataset = self.levels_dataset(data_source);
+                    let url = if dataset.is_empty() {
+                        nndc_url(&isotope)
+                    } else {
+                        dataset_page(&isotope, dataset)
+                    };
+                    ui.horizontal(|ui| {
+                        ui.hyperlink_to(egui::RichText::new("NNDC").color(Color32::RED), url);
+                        if ui.button("Retry").clicked() {
+                            Self::fetch_excitation_levels(self, data_source, true);
+                        }
+                    });
+                    ui.separator();
+                }
+
+                if data_source == DataSource::Nndc && self.ensdf_file.is_none() {
+                    self.dataset_ui(ui, index, data_source);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Window: ");
+                    ui.add(
+                        egui::DragValue::new(&mut self.level_filter_min)
+                            .suffix(" MeV")
+                            .speed(0.1)
+                            .clamp_range(0.0..=self.level_filter_max),
+                    );
+                    ui.label("to");
+                    ui.add(
+                        egui::DragValue::new(&mut self.level_filter_max)
+                            .suffix(" MeV")
+                            .speed(0.1)
+                            .clamp_range(self.level_filter_min..=f64::MAX)
+                            .custom_formatter(|value, range| {
+                                if value >= f64::MAX {
+                                    "∞".to_string()
+                                } else {
+                                    egui::emath::format_with_decimals_in_range(value, range)
+                                }
+                            })
+                            .custom_parser(|text| match text.trim() {
+                                "∞" | "inf" => Some(f64::MAX),
+                                text => text.parse().ok(),
+                            }),
+                    )
+                    .on_hover_text("Only levels inside the window are plotted, additional levels are always kept");
+                    if ui.button("Reset").clicked() {
+                        self.level_filter_min = 0.0;
+                        self.level_filter_max = f64::MAX;
+                    }
+                });
+
                 if self.excitation_levels.is_empty() {
                     ui.label("None");
+                } else {
+                    let mut summary = format!("{} levels", self.excitation_levels.len());
+                    if let Some((count, density, spacing)) = self.level_statistics(acceptance) {
+                        summary.push_str(&format!(
+                            ", {} in acceptance: {:.1} per MeV, {:.0} keV apart",
+                            count,
+                            density,
+                            spacing * 1000.0
+                        ));
+                    }
+                    ui.label(summary).on_hover_text(
+                        "Density and mean spacing of the calculated levels that reach the focal plane, \
+                         levels closer than the resolution blur together",
+                    );
                 }
 
+                self.isomer_ui(ui, index, energy_decimals);
+
                 let mut to_remove_level: Option<usize> = None;
-                for (index, level) in self.excitation_levels.iter().enumerate() {
-                    ui.horizontal(|ui| {
-                        ui.label(format!("{}: {:.3} MeV", index, level));
-                        if ui.button("-").clicked() {
-                            to_remove_level = Some(index);
+                let mut cross_section: Option<(f64, f64)> = None;
+                let mut must_see: Option<f64> = None;
+                // only the rows in view are laid out, heavy nuclei can have hundreds of levels
+                let row_height = ui.spacing().interact_size.y;
+                egui::ScrollArea::vertical()
+                    .id_source(format!("Reaction {} Levels", index))
+                    .max_height(LEVEL_ROWS_SHOWN as f32 * row_height)
+                    .show_rows(ui, row_height, self.excitation_levels.len(), |ui, rows| {
+                        for (index, level) in self.excitation_levels[rows.clone()]
+                            .iter()
+                            .enumerate()
+                            .map(|(offset, level)| (rows.start + offset, level))
+                        {
+                            ui.horizontal(|ui| {
+                                let starred = self.must_see.contains(&level.energy);
+                                if Self::must_see_button(ui, starred) {
+                                    must_see = Some(level.energy);
+                                }
+
+                                let mut label =
+                                    format!("{}: {:.*}", index, energy_decimals, level.energy);
+                                if let Some(uncertainty) = level.uncertainty {
+                                    label.push_str(&format!(" ± {:.*}", energy_decimals, uncertainty));
+                                }
+                                label.push_str(" MeV");
+                                if !level.jpi.is_empty() {
+                                    label.push_str(&format!(" {}", level.jpi));
+                                }
+                                if level.is_isomer() {
+                                    label.push_str(&format!(" ⌛ {}", level.half_life));
+                                }
+                                ui.label(label);
+                                if show_cross_sections {
+                                    let mut relative = self.relative_cross_section(level.energy);
+                                    if ui
+                                        .add(
+                                            egui::DragValue::new(&mut relative)
+                                                .prefix("σ × ")
+                                                .speed(0.01)
+                                                .clamp_range(0.0..=f64::MAX),
+                                        )
+                                        .on_hover_text("Cross section relative to the flat one")
+                                        .changed()
+                                    {
+                                        cross_section = Some((level.energy, relative));
+                                    }
+                                }
+                                if ui.button("-").clicked() {
+                                    to_remove_level = Some(index);
+                                }
+                            });
                         }
                     });
+
+                if let Some((excitation, relative)) = cross_section {
+                    self.set_relative_cross_section(excitation, relative);
                 }
 
                 if let Some(index) = to_remove_level {
                     self.excitation_levels.remove(index);
                 }
 
+                self.ejectile_levels_ui(ui, index, data_source, energy_decimals);
+
                 ui.separator();
 
                 ui.label("Additional Levels");
 
                 ui.horizontal(|ui| {
-                    ui.add(
-                        egui::DragValue::new(&mut self.add_excitation_level)
-                            .prefix("Custom: ")
-                            .suffix(" MeV")
-                            .speed(0.1)
-                            .clamp_range(0.0..=f64::MAX),
-                    );
+                    ui.radio_value(&mut self.add_as_rho_marker, false, "Energy");
+                    ui.radio_value(&mut self.add_as_rho_marker, true, "Rho Marker")
+                        .on_hover_text("A fixed rho line, e.g. a known contaminant peak");
+                });
+
+                ui.horizontal(|ui| {
+                    if self.add_as_rho_marker {
+                        ui.add(
+                            rho_drag_value(&mut self.add_excitation_level, rho_unit)
+                                .prefix("Custom: "),
+                        );
+                    } else {
+                        ui.add(
+                            egui::DragValue::new(&mut self.add_excitation_level)
+                                .prefix("Custom: ")
+                                .suffix(" MeV")
+                                .speed(0.1)
+                                .clamp_range(0.0..=f64::MAX),
+                        );
+                    }
                     if ui.button("+").clicked() {
-                        self.additional_excitation_levels
-                            .push(self.add_excitation_level);
-                        log::info!("Added new excitation level: {}", self.add_excitation_level);
+                        if self.add_as_rho_marker {
+                            self.rho_markers.push(self.add_excitation_level);
+                            log::info!("Added new rho marker: {}", self.add_excitation_level);
+                        } else {
+                            self.additional_excitation_levels
+                                .push((String::new(), self.add_excitation_level));
+                            log::info!(
+                                "Added new excitation level: {}",
+                                self.add_excitation_level
+                            );
+                        }
                     }
                 });
 
+                // keeps the fetched levels that land on the detector, using the last calculated rho
+                let on_plane = self.levels_on_plane(acceptance);
+                if ui
+                    .add_enabled(
+                        !on_plane.is_empty(),
+                        egui::Button::new(format!("Add {} in acceptance", on_plane.len())),
+                    )
+                    .on_hover_text("Add every fetched level inside the focal plane acceptance, press Calculate first")
+                    .clicked()
+                {
+                    self.additional_excitation_levels
+                        .extend(on_plane.into_iter().map(|energy| (String::new(), energy)));
+                }
+
                 let mut to_remove: Option<usize> = None;
                 if !self.additional_excitation_levels.is_empty() {
-                    for (index, level) in self.additional_excitation_levels.iter().enumerate() {
+                    for (index, (name, level)) in
+                        self.additional_excitation_levels.iter_mut().enumerate()
+                    {
                         ui.horizontal(|ui| {
+                            if Self::must_see_button(ui, self.must_see.contains(level)) {
+                                must_see = Some(*level);
+                            }
                             ui.label(format!("Energy: {} MeV", level));
+                            ui.add(
+                                egui::TextEdit::singleline(name)
+                                    .hint_text("Name")
+                                    .desired_width(120.0),
+                            )
+                            .on_hover_text("e.g. suspected 2+ state, shown on the bar and its label");
                             if ui.button("-").clicked() {
                                 to_remove = Some(index);
                             }
@@ -117,457 +891,6349 @@ impl Reaction {
                         self.additional_excitation_levels.remove(index);
                     }
                 }
+
+                let mut to_remove: Option<usize> = None;
+                for (index, rho) in self.rho_markers.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Rho Marker: {} {}",
+                            rho_unit.convert(*rho),
+                            rho_unit.label()
+                        ));
+                        if ui.button("-").clicked() {
+                            to_remove = Some(index);
+                        }
+                    });
+                }
+
+                if let Some(index) = to_remove {
+                    self.rho_markers.remove(index);
+                }
+
+                if let Some(excitation) = must_see {
+                    match self.must_see.iter().position(|energy| *energy == excitation) {
+                        Some(position) => {
+                            self.must_see.remove(position);
+                        }
+                        None => self.must_see.push(excitation),
+                    }
+                }
                 // });
             });
     }
 
-    pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
+    // collapsed by default so the residual's levels stay on top
+    fn ejectile_levels_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        data_source: DataSource,
+        energy_decimals: usize,
+    ) {
+        let isotope = self
+            .ejectile_data
+            .as_ref()
+            .map_or("None", |data| &data.isotope)
+            .to_string();
+
+        egui::CollapsingHeader::new(format!("Ejectile Levels ({})", isotope))
+            .id_source(format!("Reaction {} Ejectile Levels", index))
+            .default_open(false)
+            .show(ui, |ui| {
+                if ui
+                    .button("Get Ejectile Levels")
+                    .on_hover_text(
+                        "Adopted levels of the ejectile, for particle-gamma coincidences",
+                    )
+                    .clicked()
+                {
+                    Self::fetch_ejectile_levels(self, data_source);
+                }
+                if self.ejectile_levels_queued {
+                    ui.spinner();
+                }
+
+                if let Some(error) = &self.ejectile_levels_error {
+                    ui.colored_label(Color32::RED, format!("{}: {}", isotope, error));
+                }
+
+                if self.ejectile_levels.is_empty() {
+                    ui.label("None");
+                }
+                for level in &self.ejectile_levels {
+                    let mut label = format!("{:.*} MeV", energy_decimals, level.energy);
+                    if !level.jpi.is_empty() {
+                        label.push_str(&format!(" {}", level.jpi));
+                    }
+                    ui.label(label);
+                }
+            });
+    }
+
+    // star toggle, true when clicked
+    fn must_see_button(ui: &mut egui::Ui, starred: bool) -> bool {
+        ui.selectable_label(starred, if starred { "★" } else { "☆" })
+            .on_hover_text(
+                "Must see, the field and angle search tries to fit it on the focal plane",
+            )
+            .clicked()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_levels_from_csv(&mut self) {
+        use super::excitation_fetcher::parse_levels_csv;
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv", "txt"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let (levels, skipped) = parse_levels_csv(&contents);
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+                let mut message = format!("Loaded {} levels from {}", levels.len(), file_name);
+                if skipped > 0 {
+                    message.push_str(&format!(" (skipped {} malformed lines)", skipped));
+                }
+                log::info!("{}", message);
+
+                self.excitation_levels = levels;
+                self.levels_source = format!("imported from {}", file_name);
+                self.levels_fetched_at.clear();
+                self.fetch_error = None;
+                self.import_message = Some(message);
+            }
+            Err(e) => {
+                log::error!("Failed to read {:?}: {}", path, e);
+                self.import_message = Some(format!("Failed to read {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    pub fn settings_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        data_source: DataSource,
+        evaluation: MassEvaluation,
+    ) {
         ui.label("Target: ");
+        Self::nucleus_input(
+            ui,
+            &mut self.target_name,
+            &mut self.target_z,
+            &mut self.target_a,
+            &mut self.name_error,
+        );
         ui.add(egui::DragValue::new(&mut self.target_z).prefix("Z: "));
         ui.add(egui::DragValue::new(&mut self.target_a).prefix("A: "));
 
         ui.separator();
 
         ui.label("Projectile: ");
+        Self::nucleus_input(
+            ui,
+            &mut self.projectile_name,
+            &mut self.projectile_z,
+            &mut self.projectile_a,
+            &mut self.name_error,
+        );
         ui.add(egui::DragValue::new(&mut self.projectile_z).prefix("Z: "));
         ui.add(egui::DragValue::new(&mut self.projectile_a).prefix("A: "));
+        Self::light_ion_menu(
+            ui,
+            &mut self.projectile_name,
+            &mut self.projectile_z,
+            &mut self.projectile_a,
+        );
 
         ui.separator();
 
         ui.label("Ejectile: ");
+        Self::nucleus_input(
+            ui,
+            &mut self.ejectile_name,
+            &mut self.ejectile_z,
+            &mut self.ejectile_a,
+            &mut self.name_error,
+        );
         ui.add(egui::DragValue::new(&mut self.ejectile_z).prefix("Z: "));
         ui.add(egui::DragValue::new(&mut self.ejectile_a).prefix("A: "));
+        Self::light_ion_menu(
+            ui,
+            &mut self.ejectile_name,
+            &mut self.ejectile_z,
+            &mut self.ejectile_a,
+        );
+
+        if ui
+            .button("Elastic")
+            .on_hover_text(
+                "Use the projectile as the ejectile, the usual calibration on a new target",
+            )
+            .clicked()
+        {
+            self.ejectile_z = self.projectile_z;
+            self.ejectile_a = self.projectile_a;
+            self.ejectile_name = self.projectile_name.clone();
+            Self::populate_reaction_data(self, evaluation);
+        }
+
+        ui.separator();
+
+        ui.checkbox(&mut self.manual_residual, "Advanced")
+            .on_hover_text(
+                "Set the residual nucleus directly instead of from Z and A conservation",
+            );
+        if self.manual_residual {
+            ui.label("Residual: ");
+            ui.add(egui::DragValue::new(&mut self.resid_z).prefix("Z: "));
+            ui.add(egui::DragValue::new(&mut self.resid_a).prefix("A: "));
+        }
+
+        if ui
+            .checkbox(&mut self.inverse_kinematics, "Inverse")
+            .on_hover_text(
+                "Inverse kinematics, the target nucleus is the beam at the beam energy and the projectile is at rest in the target",
+            )
+            .changed()
+        {
+            Self::populate_reaction_data(self, evaluation);
+        }
 
         ui.separator();
 
+        self.mass_overrides_ui(ui);
+
         ui.label(self.reaction_identifier.to_string());
+        if self.is_elastic() {
+            ui.label("(elastic)")
+                .on_hover_text("Q = 0, the ground state is the calibration peak");
+        }
 
-        if ui.button("Get Reaction").clicked() {
-            Self::populate_reaction_data(self);
-            Self::fetch_excitation_levels(self);
+        if ui
+            .button("Get Reaction")
+            .on_hover_text("Hold shift to fetch the levels again instead of reusing saved ones")
+            .clicked()
+        {
+            let refresh = ui.input(|i| i.modifiers.shift);
+            Self::populate_reaction_data(self, evaluation);
+            Self::fetch_excitation_levels(self, data_source, refresh);
         }
-    }
 
-    pub fn draw(&self, plot_ui: &mut egui_plot::PlotUi, y_offset: f64) {
-        let color = self.color;
+        // the residual of the last Get Reaction, Z, A and the identifier are left alone
+        if ui
+            .add_enabled(
+                self.resid_data.is_some(),
+                egui::Button::new("Refresh Levels"),
+            )
+            .on_hover_text("Fetch the residual's levels again from the selected source")
+            .on_disabled_hover_text("Get Reaction first")
+            .clicked()
+        {
+            Self::fetch_excitation_levels(self, data_source, true);
+        }
+        if self.levels_queued {
+            ui.spinner();
+        }
 
-        let mut bars = Vec::new();
-        for (excitation, rho) in &self.rho_values {
-            let bar = Bar {
-                orientation: Orientation::Vertical,
-                argument: *rho,
-                value: 0.50,
-                bar_width: 0.01,
-                fill: color,
-                stroke: Stroke::new(1.0, color),
-                name: format!("E = {:.3} MeV\nrho = {:.3}\n", *excitation, *rho),
-                base_offset: Some(y_offset),
-            };
+        if !self.excitation_levels.is_empty() {
+            if self.levels_fetched {
+                ui.label("fetched");
+            } else {
+                ui.weak("from cache").on_hover_text(
+                    "Levels saved with the session, Refresh Levels to fetch them again",
+                );
+            }
+            if !self.levels_fetched_at.is_empty() {
+                ui.weak(&self.levels_fetched_at)
+                    .on_hover_text("When these levels were retrieved from NNDC");
+            }
+        }
 
-            bars.push(bar);
+        if let Some(threshold) = self.threshold.filter(|threshold| *threshold > 0.0) {
+            ui.label(format!("Threshold: {:.3} MeV", threshold))
+                .on_hover_text("Minimum beam energy to populate the ground state");
         }
 
-        let barchart = BarChart::new(bars)
-            .name(self.reaction_identifier.clone())
-            .color(color)
-            .highlight(true);
+        ui.add(
+            egui::TextEdit::singleline(&mut self.notes)
+                .hint_text("Notes")
+                .desired_width(150.0),
+        )
+        .on_hover_text("Why this reaction is on the plot, saved with the project");
 
-        plot_ui.bar_chart(barchart);
+        if let Some(error) = &self.name_error {
+            ui.colored_label(Color32::RED, error);
+        }
+
+        if let Some(error) = &self.calculation_error {
+            ui.colored_label(Color32::RED, error)
+                .on_hover_text("Fix Z and A, then Get Reaction and Calculate again");
+        }
     }
 
-    fn populate_reaction_data(reaction: &mut Reaction) {
-        reaction.resid_z = reaction.target_z + reaction.projectile_z - reaction.ejectile_z;
-        reaction.resid_a = reaction.target_a + reaction.projectile_a - reaction.ejectile_a;
+    // A reaction with the given nuclear data in place of the mass table, the masses are
+    // kept as overrides so recalculating doesn't replace them
+    #[cfg(test)]
+    fn from_nuclei(
+        target: NuclearData,
+        projectile: NuclearData,
+        ejectile: NuclearData,
+        resid: NuclearData,
+        color: Color32,
+    ) -> Self {
+        Reaction {
+            target_z: target.z as i32,
+            target_a: target.a as i32,
+            target_mass_override: Some(target.mass),
+            target_data: Some(target),
+            projectile_z: projectile.z as i32,
+            projectile_a: projectile.a as i32,
+            projectile_mass_override: Some(projectile.mass),
+            projectile_data: Some(projectile),
+            ejectile_z: ejectile.z as i32,
+            ejectile_a: ejectile.a as i32,
+            ejectile_mass_override: Some(ejectile.mass),
+            ejectile_data: Some(ejectile),
+            resid_z: resid.z as i32,
+            resid_a: resid.a as i32,
+            resid_mass_override: Some(resid.mass),
+            resid_data: Some(resid),
+            manual_residual: true,
+            ..Reaction::new(color)
+        }
+    }
 
-        reaction.target_data =
-            NuclearData::get_data(reaction.target_z as u32, reaction.target_a as u32);
-        reaction.projectile_data =
-            NuclearData::get_data(reaction.projectile_z as u32, reaction.projectile_a as u32);
-        reaction.ejectile_data =
-            NuclearData::get_data(reaction.ejectile_z as u32, reaction.ejectile_a as u32);
-        reaction.resid_data =
-            NuclearData::get_data(reaction.resid_z as u32, reaction.resid_a as u32);
+    fn from_preset(preset: &ReactionPreset, color: Color32) -> Self {
+        let (_, target, projectile, ejectile) = *preset;
+        Reaction {
+            target_z: target.0,
+            target_a: target.1,
+            projectile_z: projectile.0,
+            projectile_a: projectile.1,
+            ejectile_z: ejectile.0,
+            ejectile_a: ejectile.1,
+            ..Reaction::new(color)
+        }
+    }
 
-        reaction.reaction_identifier = format!(
-            "{}({},{}){}",
-            reaction
-                .target_data
-                .as_ref()
-                .map_or("None", |data| &data.isotope),
-            reaction
-                .projectile_data
-                .as_ref()
-                .map_or("None", |data| &data.isotope),
-            reaction
-                .ejectile_data
+    fn mass_overrides_ui(&mut self, ui: &mut egui::Ui) {
+        let overrides = [
+            ("Target", &mut self.target_mass_override, &self.target_data),
+            (
+                "Projectile",
+                &mut self.projectile_mass_override,
+                &self.projectile_data,
+            ),
+            (
+                "Ejectile",
+                &mut self.ejectile_mass_override,
+                &self.ejectile_data,
+            ),
+            ("Residual", &mut self.resid_mass_override, &self.resid_data),
+        ];
+        let overridden = overrides.iter().any(|(_, mass, _)| mass.is_some());
+
+        ui.menu_button("Masses", |ui| {
+            ui.label("Nuclear masses used by Calculate");
+            for (name, mass, data) in overrides {
+                ui.horizontal(|ui| {
+                    let table_mass = data.as_ref().map(|data| data.mass);
+
+                    let mut custom = mass.is_some();
+                    if ui.checkbox(&mut custom, name).changed() {
+                        *mass = custom.then(|| table_mass.unwrap_or_default());
+                    }
+
+                    match mass {
+                        Some(mass) => {
+                            ui.add(egui::DragValue::new(mass).suffix(" MeV").speed(0.001));
+                        }
+                        None => {
+                            ui.label(table_mass.map_or("Not in table".to_string(), |mass| {
+                                format!("{:.3} MeV", mass)
+                            }));
+                        }
+                    }
+                });
+            }
+        });
+
+        if overridden {
+            ui.colored_label(Color32::from_rgb(255, 165, 0), "overridden")
+                .on_hover_text("Some masses are custom values rather than the mass evaluation");
+        }
+    }
+
+    // patches the looked up nuclei with the custom masses, or restores the table values
+    fn apply_mass_overrides(&mut self, evaluation: MassEvaluation) {
+        let particles = [
+            (
+                self.target_z,
+                self.target_a,
+                self.target_mass_override,
+                &mut self.target_data,
+            ),
+            (
+                self.projectile_z,
+                self.projectile_a,
+                self.projectile_mass_override,
+                &mut self.projectile_data,
+            ),
+            (
+                self.ejectile_z,
+                self.ejectile_a,
+                self.ejectile_mass_override,
+                &mut self.ejectile_data,
+            ),
+            (
+                self.resid_z,
+                self.resid_a,
+                self.resid_mass_override,
+                &mut self.resid_data,
+            ),
+        ];
+
+        for (z, a, mass, data) in particles {
+            let table = NuclearData::get_data(z as u32, a as u32, evaluation);
+            *data = match (mass, table) {
+                (Some(mass), Some(table)) => Some(NuclearData {
+                    mass,
+                    source: MassSource::User,
+                    ..table
+                }),
+                (Some(mass), None) => Some(NuclearData::with_mass(z as u32, a as u32, mass)),
+                (None, table) => table,
+            };
+        }
+    }
+
+    // fills in Z and A of a common light ion
+    fn light_ion_menu(ui: &mut egui::Ui, name: &mut String, z: &mut i32, a: &mut i32) {
+        ui.menu_button("⏷", |ui| {
+            for (ion, ion_z, ion_a) in LIGHT_IONS {
+                if ui.button(ion).clicked() {
+                    *name = ion.to_string();
+                    *z = ion_z;
+                    *a = ion_a;
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text("p, d, t, 3He or α");
+    }
+
+    // name entry for a nucleus, fills in Z and A when enter is pressed
+    fn nucleus_input(
+        ui: &mut egui::Ui,
+        name: &mut String,
+        z: &mut i32,
+        a: &mut i32,
+        error: &mut Option<String>,
+    ) {
+        let response = ui.add(
+            egui::TextEdit::singleline(name)
+                .hint_text("12C")
+                .desired_width(40.0),
+        );
+
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            match parse_isotope(name) {
+                Ok((parsed_z, parsed_a)) => {
+                    *z = parsed_z as i32;
+                    *a = parsed_a as i32;
+                    *error = None;
+                }
+                Err(e) => *error = Some(e),
+            }
+        }
+    }
+
+    pub fn draw(&self, plot_ui: &mut egui_plot::PlotUi, y_offset: f64, options: &DrawOptions) {
+        // an empty chart keeps the legend entry so the reaction can be clicked back on
+        if !self.visible {
+            plot_ui.bar_chart(
+                BarChart::new(Vec::new())
+                    .name(&options.legend_name)
+                    .color(self.color),
+            );
+            return;
+        }
+
+        let orientation = options.orientation;
+        let (rho_min, rho_max) = options.acceptance;
+        let inside = |value: &&RhoValue| (rho_min..=rho_max).contains(&value.rho);
+
+        let mut bars = Vec::new();
+        let groups = Self::unresolved_groups(
+            self.drawn_values(options.ground_state_only),
+            options.rho_resolution,
+        );
+        for group in groups {
+            let outside = !group.iter().any(inside);
+            if outside && options.outside_acceptance == OutsideAcceptance::Hide {
+                continue;
+            }
+            let fade = outside && options.outside_acceptance == OutsideAcceptance::Fade;
+            // merged and single bars both scale with their summed counts, so their heights compare
+            let counts = options
+                .yields
                 .as_ref()
-                .map_or("None", |data| &data.isotope),
-            reaction
+                .map(|(settings, max_counts)| (self.group_counts(&group, settings), max_counts));
+
+            if let [first, .., last] = group.as_slice() {
+                let mut bar = self.merged_bar(first, last, &group, y_offset, options);
+                if let Some((counts, max_counts)) = counts {
+                    bar.value *= counts / max_counts;
+                    bar.name.push_str(&format!("≈ {:.0} counts\n", counts));
+                }
+                if fade {
+                    bar.fill = faded(bar.fill);
+                    bar.stroke.color = faded(bar.stroke.color);
+                }
+                bars.push(bar);
+                continue;
+            }
+
+            let value = group[0];
+            // levels without a known J keep the reaction color
+            let color = match value.two_j() {
+                Some(two_j) if options.color_by_spin => spin_color(two_j),
+                _ => self.color,
+            };
+            let color = if fade { faded(color) } else { color };
+            let fill = if self.contaminant {
+                color.gamma_multiply(0.3)
+            } else {
+                color
+            };
+
+            let decimals = options.energy_decimals;
+            let mut name = if value.name.is_empty() {
+                String::new()
+            } else {
+                format!("{}\n", value.name)
+            };
+            name.push_str(&match value.uncertainty {
+                Some(uncertainty) => format!(
+                    "E = {:.*} ± {:.*} MeV\n",
+                    decimals, value.excitation, decimals, uncertainty
+                ),
+                None => format!("E = {:.*} MeV\n", decimals, value.excitation),
+            });
+            if !value.jpi.is_empty() {
+                name.push_str(&format!("Jπ = {}\n", value.jpi));
+            }
+            if !value.half_life.is_empty() {
+                name.push_str(&format!("T1/2 = {}\n", value.half_life));
+            }
+            if value.isomer {
+                name.push_str("isomer\n");
+            }
+            let rho_unit = options.rho_unit;
+            name.push_str(&format!("rho = {:.4}", rho_unit.convert(value.rho)));
+            if let Some(rho_uncertainty) = value.rho_uncertainty {
+                name.push_str(&format!(" ± {:.4}", rho_unit.convert(rho_uncertainty)));
+            }
+            name.push_str(&format!(" {}\n", rho_unit.label()));
+            name.push_str(&format!("T = {:.3} MeV\n", value.ejectile_energy));
+            name.push_str(&format!("p = {:.3} MeV/c\n", value.momentum));
+            name.push_str(&format!(
+                "Bρ = {:.4} {} {}\n",
+                brho_in(value.brho, options.field_unit, rho_unit),
+                options.field_unit.label(),
+                rho_unit.label()
+            ));
+            if inside(&value) {
+                name.push_str("inside acceptance\n");
+            } else {
+                name.push_str("outside acceptance\n");
+            }
+
+            // levels matching the rho search stand out with a heavier outline
+            let stroke_width = match options.search_window {
+                _ if options.highlighted_excitation == Some(value.excitation) => 6.0,
+                Some((min, max)) if (min..=max).contains(&value.rho) => 4.0,
+                _ => 1.0,
+            };
+
+            let mut bar_height = options.bar_height;
+            if let Some((counts, max_counts)) = counts {
+                bar_height *= counts / max_counts;
+                name.push_str(&format!("≈ {:.0} counts\n", counts));
+            }
+
+            let bar = Bar {
+                orientation: orientation.bar_orientation(),
+                argument: value.rho,
+                value: bar_height,
+                bar_width: options.bar_width,
+                fill,
+                stroke: Stroke::new(stroke_width, color),
+                name,
+                base_offset: Some(y_offset),
+            };
+
+            bars.push(bar);
+
+            // error bar along rho at half height so nearby levels can be judged as resolvable
+            if let Some(rho_uncertainty) = value.rho_uncertainty {
+                let y = y_offset + options.bar_height / 2.0;
+                plot_ui.line(
+                    Line::new(PlotPoints::new(vec![
+                        orientation.point(value.rho - rho_uncertainty, y),
+                        orientation.point(value.rho + rho_uncertainty, y),
+                    ]))
+                    .color(color),
+                );
+            }
+        }
+
+        if self.style != BarStyle::Solid || self.hatch {
+            for bar in &mut bars {
+                self.draw_bar_style(plot_ui, bar, orientation);
+            }
+        }
+
+        let color = self.color;
+        let barchart = BarChart::new(bars)
+            .name(&options.legend_name)
+            .color(color)
+            .highlight(true);
+
+        plot_ui.bar_chart(barchart);
+
+        for field in &options.field_settings {
+            self.draw_field_setting(plot_ui, y_offset, options, *field);
+        }
+
+        // markers sit at a fixed rho, dashed so they aren't mistaken for levels
+        for rho in &self.rho_markers {
+            plot_ui.line(
+                Line::new(PlotPoints::new(vec![
+                    orientation.point(*rho, y_offset),
+                    orientation.point(*rho, y_offset + options.bar_height),
+                ]))
+                .color(color)
+                .width(2.0)
+                .style(LineStyle::dashed_loose())
+                .name(format!(
+                    "{} rho = {:.4} {}",
+                    options.legend_name,
+                    options.rho_unit.convert(*rho),
+                    options.rho_unit.label()
+                )),
+            );
+        }
+
+        if options.show_peak_labels {
+            self.draw_peak_labels(
+                plot_ui,
+                self.drawn_values(options.ground_state_only),
+                y_offset + options.bar_height,
+                orientation,
+                options.energy_decimals,
+            );
+        }
+    }
+
+    // every level, or only the lowest one when tuning on the ground states. `rho_values` is left alone.
+    fn drawn_values(&self, ground_state_only: bool) -> Vec<&RhoValue> {
+        if ground_state_only {
+            self.rho_values
+                .iter()
+                .min_by(|a, b| a.excitation.total_cmp(&b.excitation))
+                .into_iter()
+                .collect()
+        } else {
+            self.rho_values.iter().collect()
+        }
+    }
+
+    // Levels sorted by rho and chained into groups whose neighbours are closer than `resolution` (cm)
+    fn unresolved_groups(mut values: Vec<&RhoValue>, resolution: f64) -> Vec<Vec<&RhoValue>> {
+        values.sort_by(|a, b| a.rho.total_cmp(&b.rho));
+
+        let mut groups: Vec<Vec<&RhoValue>> = Vec::new();
+        for value in values {
+            match groups.last_mut() {
+                Some(group) if value.rho - group[group.len() - 1].rho < resolution => {
+                    group.push(value)
+                }
+                _ => groups.push(vec![value]),
+            }
+        }
+        groups
+    }
+
+    // one hollow bar spanning the levels the detector can't separate
+    fn merged_bar(
+        &self,
+        first: &RhoValue,
+        last: &RhoValue,
+        group: &[&RhoValue],
+        y_offset: f64,
+        options: &DrawOptions,
+    ) -> Bar {
+        let (low, high) = group
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(low, high), value| {
+                (low.min(value.excitation), high.max(value.excitation))
+            });
+
+        let decimals = options.energy_decimals;
+        let mut name = format!(
+            "Unresolved: E = {:.*} - {:.*} MeV\n",
+            decimals, low, decimals, high
+        );
+        for value in group {
+            name.push_str(&format!(
+                "  E = {:.*} MeV {} rho = {:.4} {}\n",
+                decimals,
+                value.excitation,
+                value.jpi,
+                options.rho_unit.convert(value.rho),
+                options.rho_unit.label()
+            ));
+        }
+
+        Bar {
+            orientation: options.orientation.bar_orientation(),
+            argument: (first.rho + last.rho) / 2.0,
+            value: options.bar_height,
+            bar_width: (last.rho - first.rho).max(options.bar_width),
+            fill: self.color.gamma_multiply(0.15),
+            stroke: Stroke::new(2.0, self.color),
+            name,
+            base_offset: Some(y_offset),
+        }
+    }
+
+    // Replaces the bar's own outline with a dashed or dotted one and its fill with hatching.
+    // The bar itself stays for the hover text.
+    fn draw_bar_style(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        bar: &mut Bar,
+        orientation: PlotOrientation,
+    ) {
+        const HATCH_LINES: usize = 6;
+
+        let base = bar.base_offset.unwrap_or(0.0);
+        let (left, right) = (
+            bar.argument - bar.bar_width / 2.0,
+            bar.argument + bar.bar_width / 2.0,
+        );
+        let (bottom, top) = (base, base + bar.value);
+
+        if self.style != BarStyle::Solid {
+            plot_ui.line(
+                Line::new(PlotPoints::new(vec![
+                    orientation.point(left, bottom),
+                    orientation.point(left, top),
+                    orientation.point(right, top),
+                    orientation.point(right, bottom),
+                    orientation.point(left, bottom),
+                ]))
+                .color(bar.stroke.color)
+                .width(bar.stroke.width.max(1.5))
+                .style(self.style.line_style()),
+            );
+            bar.stroke = Stroke::new(bar.stroke.width, Color32::TRANSPARENT);
+        }
+
+        if self.hatch {
+            let step = bar.value / HATCH_LINES as f64;
+            for line in 0..HATCH_LINES {
+                let y = bottom + line as f64 * step;
+                plot_ui.line(
+                    Line::new(PlotPoints::new(vec![
+                        orientation.point(left, y),
+                        orientation.point(right, y + step),
+                    ]))
+                    .color(bar.stroke.color.gamma_multiply(0.8))
+                    .width(1.0),
+                );
+            }
+            bar.fill = Color32::TRANSPARENT;
+        }
+    }
+
+    // Faded, shorter bars where the levels land at another field, rho scales as 1/B so no recalculation is needed
+    fn draw_field_setting(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        y_offset: f64,
+        options: &DrawOptions,
+        field: f64,
+    ) {
+        if field <= 0.0 {
+            return;
+        }
+
+        let color = self.color.gamma_multiply(0.35);
+        let scale = options.magnetic_field / field;
+        let bars = self
+            .drawn_values(options.ground_state_only)
+            .into_iter()
+            .map(|value| Bar {
+                orientation: options.orientation.bar_orientation(),
+                argument: value.rho * scale,
+                value: options.bar_height * 0.6,
+                bar_width: options.bar_width,
+                fill: color,
+                stroke: Stroke::new(1.0, color),
+                name: format!(
+                    "B = {:.4} {}\nE = {:.*} MeV\nrho = {:.4} {}\n",
+                    options.field_unit.convert(field),
+                    options.field_unit.label(),
+                    options.energy_decimals,
+                    value.excitation,
+                    options.rho_unit.convert(value.rho * scale),
+                    options.rho_unit.label()
+                ),
+                base_offset: Some(y_offset),
+            })
+            .collect();
+
+        plot_ui.bar_chart(
+            BarChart::new(bars)
+                .name(format!(
+                    "{} at {:.4} {}",
+                    options.legend_name,
+                    options.field_unit.convert(field),
+                    options.field_unit.label()
+                ))
+                .color(color),
+        );
+    }
+
+    // Labels sit past the end of the bars, labels that would overlap their neighbour are pushed out a row
+    fn draw_peak_labels(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        mut values: Vec<&RhoValue>,
+        y_top: f64,
+        orientation: PlotOrientation,
+        decimals: usize,
+    ) {
+        const ROWS: usize = 3;
+        const ROW_HEIGHT: f64 = 0.15;
+
+        // along rho a label takes up its width when vertical and its height when horizontal
+        let dvalue_dpos = plot_ui.transform().dvalue_dpos();
+        let (min_spacing, anchor) = match orientation {
+            PlotOrientation::RhoAlongX => {
+                (45.0 * dvalue_dpos[0].abs(), egui::Align2::CENTER_BOTTOM)
+            }
+            PlotOrientation::RhoAlongY => (14.0 * dvalue_dpos[1].abs(), egui::Align2::LEFT_CENTER),
+        };
+
+        values.sort_by(|a, b| a.rho.total_cmp(&b.rho));
+
+        let mut last_rho = [f64::NEG_INFINITY; ROWS];
+        for value in values {
+            let row = (0..ROWS)
+                .find(|&row| value.rho - last_rho[row] >= min_spacing)
+                .unwrap_or_else(|| {
+                    (0..ROWS)
+                        .min_by(|&a, &b| last_rho[a].total_cmp(&last_rho[b]))
+                        .unwrap_or(0)
+                });
+            last_rho[row] = value.rho;
+
+            let position = orientation.point(value.rho, y_top + row as f64 * ROW_HEIGHT);
+            let mut label = format!("{:.*}", decimals, value.excitation);
+            if !value.name.is_empty() {
+                label.push_str(&format!(" {}", value.name));
+            }
+            plot_ui.text(
+                Text::new(position.into(), label)
+                    .anchor(anchor)
+                    .color(self.color),
+            );
+        }
+    }
+
+    // the ejectile is the projectile and the residual the target
+    pub fn is_elastic(&self) -> bool {
+        self.ejectile_z == self.projectile_z
+            && self.ejectile_a == self.projectile_a
+            && self.resid_z == self.target_z
+            && self.resid_a == self.target_a
+            && self.projectile_a > 0
+    }
+
+    fn reads_named_dataset(&self, data_source: DataSource) -> bool {
+        data_source == DataSource::Nndc && !self.nndc_dataset.is_empty()
+    }
+
+    // Levels and where they came from, an ENSDF file picked for the reaction wins over the data source
+    fn get_levels(
+        &self,
+        isotope: &str,
+        data_source: DataSource,
+    ) -> Result<(Vec<Level>, String), String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.ensdf_file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let levels = super::ensdf::parse_ensdf_levels(&contents, isotope)?;
+            let file_name = std::path::Path::new(path)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            return Ok((levels, format!("ENSDF file {}", file_name)));
+        }
+
+        let levels = data_source.get_levels(isotope, &self.nndc_dataset)?;
+        Ok((levels, self.levels_provenance(data_source)))
+    }
+
+    // NNDC dataset the levels are read from, empty for the adopted levels
+    fn levels_dataset(&self, data_source: DataSource) -> &str {
+        if self.reads_named_dataset(data_source) {
+            &self.nndc_dataset
+        } else {
+            ""
+        }
+    }
+
+    fn levels_provenance(&self, data_source: DataSource) -> String {
+        let mut source = data_source.provenance();
+        if self.reads_named_dataset(data_source) {
+            source.push_str(&format!(", {}", self.nndc_dataset));
+        }
+        source
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ensdf_ui(&mut self, ui: &mut egui::Ui, data_source: DataSource) {
+        match self.ensdf_file.clone() {
+            Some(path) => {
+                let file_name = std::path::Path::new(&path)
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                ui.label(format!("ENSDF: {}", file_name))
+                    .on_hover_text(path);
+                if ui
+                    .small_button("x")
+                    .on_hover_text("Go back to the selected levels source")
+                    .clicked()
+                {
+                    self.ensdf_file = None;
+                    Self::fetch_excitation_levels(self, data_source, true);
+                }
+            }
+            None => {
+                if ui
+                    .button("Load ENSDF")
+                    .on_hover_text(
+                        "Read the adopted levels of the residual from a local ENSDF file",
+                    )
+                    .clicked()
+                {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        self.ensdf_file = Some(path.display().to_string());
+                        Self::fetch_excitation_levels(self, data_source, true);
+                    }
+                }
+            }
+        }
+    }
+
+    // Adopted levels or one named NNDC dataset, e.g. a single measurement to compare against
+    fn dataset_ui(&mut self, ui: &mut egui::Ui, index: usize, data_source: DataSource) {
+        let mut named = !self.nndc_dataset.is_empty() || self.naming_dataset;
+        let mut fetch = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Dataset: ");
+            egui::ComboBox::from_id_source(format!("Reaction {} Dataset", index))
+                .selected_text(if named { "Named" } else { "Adopted Levels" })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut named, false, "Adopted Levels");
+                    ui.selectable_value(&mut named, true, "Named");
+                });
+
+            if named {
+                let response = ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.nndc_dataset)
+                            .hint_text("12C(d,p)")
+                            .desired_width(100.0),
+                    )
+                    .on_hover_text("NNDC dataset name, fetched on enter");
+                // typing only fetches once the name is finished
+                fetch =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            } else if !self.nndc_dataset.is_empty() {
+                self.nndc_dataset.clear();
+                fetch = true;
+            }
+        });
+
+        self.naming_dataset = named;
+        if fetch {
+            Self::fetch_excitation_levels(self, data_source, true);
+        }
+    }
+
+    // Isomers of the residual named the NUBASE way, "26mAl" or "m", "n", "p"... when there are several
+    fn isomers(&self) -> Vec<(String, &Level)> {
+        let isotope = self
+            .resid_data
+            .as_ref()
+            .map_or(self.levels_isotope.as_str(), |data| data.isotope.as_str());
+        let (mass_number, element) =
+            isotope.split_at(isotope.find(|c: char| !c.is_ascii_digit()).unwrap_or(0));
+
+        self.excitation_levels
+            .iter()
+            .filter(|level| level.is_isomer())
+            .zip(['m', 'n', 'p', 'q', 'r', 'x'])
+            .map(|(level, letter)| (format!("{}{}{}", mass_number, letter, element), level))
+            .collect()
+    }
+
+    // only shown once the fetched levels include a long-lived state, the bundled levels have no half-lives
+    fn isomer_ui(&mut self, ui: &mut egui::Ui, index: usize, energy_decimals: usize) {
+        let isomers = self.isomers();
+        if isomers.is_empty() && self.isomer.is_none() {
+            return;
+        }
+
+        let selected = match self.isomer {
+            None => "All levels".to_string(),
+            Some(energy) => isomers
+                .iter()
+                .find(|(_, level)| level.energy == energy)
+                .map_or(
+                    format!("{:.*} MeV", energy_decimals, energy),
+                    |(name, _)| name.clone(),
+                ),
+        };
+
+        let mut isomer = self.isomer;
+        ui.horizontal(|ui| {
+            ui.label("Isomer: ");
+            egui::ComboBox::from_id_source(format!("Reaction {} Isomer", index))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut isomer, None, "All levels");
+                    for (name, level) in &isomers {
+                        ui.selectable_value(
+                            &mut isomer,
+                            Some(level.energy),
+                            format!(
+                                "{} {:.*} MeV ({})",
+                                name, energy_decimals, level.energy, level.half_life
+                            ),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Plot only this long-lived state, isomers can be populated separately",
+                );
+        });
+        self.isomer = isomer;
+    }
+
+    // a named additional level at `excitation` lends its name to a fetched copy too
+    fn additional_level_name(&self, excitation: f64) -> String {
+        self.additional_excitation_levels
+            .iter()
+            .find(|(name, level)| *level == excitation && !name.is_empty())
+            .map(|(name, _)| name.clone())
+            .unwrap_or_default()
+    }
+
+    // energies of the calculated levels inside the acceptance that aren't additional levels yet
+    fn levels_on_plane(&self, acceptance: (f64, f64)) -> Vec<f64> {
+        let (rho_min, rho_max) = acceptance;
+        self.rho_values
+            .iter()
+            .filter(|value| (rho_min..=rho_max).contains(&value.rho))
+            .map(|value| value.excitation)
+            .filter(|excitation| {
+                !self
+                    .additional_excitation_levels
+                    .iter()
+                    .any(|(_, level)| level == excitation)
+            })
+            .collect()
+    }
+
+    // (count, levels per MeV, mean spacing MeV) of the calculated levels inside the acceptance,
+    // None with fewer than two since there is no spacing
+    fn level_statistics(&self, acceptance: (f64, f64)) -> Option<(usize, f64, f64)> {
+        let (rho_min, rho_max) = acceptance;
+        let excitations = self
+            .rho_values
+            .iter()
+            .filter(|value| (rho_min..=rho_max).contains(&value.rho))
+            .map(|value| value.excitation)
+            .collect::<Vec<_>>();
+        let (low, high) = excitations
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(low, high), excitation| {
+                (low.min(*excitation), high.max(*excitation))
+            });
+
+        let count = excitations.len();
+        let spacing = (high - low) / (count as f64 - 1.0);
+        (count > 1 && spacing > 0.0).then_some((count, 1.0 / spacing, spacing))
+    }
+
+    // takes what a duplicate adds, its levels only when this reaction has none
+    fn merge(&mut self, duplicate: Reaction) {
+        if self.excitation_levels.is_empty() {
+            self.excitation_levels = duplicate.excitation_levels;
+            self.levels_isotope = duplicate.levels_isotope;
+            self.levels_source = duplicate.levels_source;
+            self.levels_fetched_at = duplicate.levels_fetched_at;
+        }
+        for (name, level) in duplicate.additional_excitation_levels {
+            if !self
+                .additional_excitation_levels
+                .iter()
+                .any(|(_, energy)| *energy == level)
+            {
+                self.additional_excitation_levels.push((name, level));
+            }
+        }
+        for rho in duplicate.rho_markers {
+            if !self.rho_markers.contains(&rho) {
+                self.rho_markers.push(rho);
+            }
+        }
+        for excitation in duplicate.must_see {
+            if !self.must_see.contains(&excitation) {
+                self.must_see.push(excitation);
+            }
+        }
+        if self.ejectile_levels.is_empty() {
+            self.ejectile_levels = duplicate.ejectile_levels;
+        }
+        for (excitation, relative) in duplicate.cross_sections {
+            if !self
+                .cross_sections
+                .iter()
+                .any(|(energy, _)| *energy == excitation)
+            {
+                self.cross_sections.push((excitation, relative));
+            }
+        }
+        if !duplicate.notes.is_empty() && !self.notes.contains(&duplicate.notes) {
+            if !self.notes.is_empty() {
+                self.notes.push_str("; ");
+            }
+            self.notes.push_str(&duplicate.notes);
+        }
+    }
+
+    pub fn relative_cross_section(&self, excitation: f64) -> f64 {
+        self.cross_sections
+            .iter()
+            .find(|(energy, _)| *energy == excitation)
+            .map_or(1.0, |(_, relative)| *relative)
+    }
+
+    fn set_relative_cross_section(&mut self, excitation: f64, relative: f64) {
+        self.cross_sections
+            .retain(|(energy, _)| *energy != excitation);
+        if relative != 1.0 {
+            self.cross_sections.push((excitation, relative));
+        }
+    }
+
+    pub fn level_counts(&self, value: &RhoValue, settings: &YieldSettings) -> f64 {
+        settings.counts(
+            self.projectile_z,
+            self.target_a,
+            self.relative_cross_section(value.excitation),
+        )
+    }
+
+    // counts of one bar on the plot, the levels a merged bar spans add up
+    fn group_counts(&self, group: &[&RhoValue], settings: &YieldSettings) -> f64 {
+        group
+            .iter()
+            .map(|value| self.level_counts(value, settings))
+            .sum()
+    }
+
+    // tab separated energies and rho for pasting into an elog or spreadsheet, the energies at
+    // full precision
+    fn rho_values_tsv(&self, rho_unit: RhoUnit) -> String {
+        let mut text = format!("E (MeV)\tRho ({})\n", rho_unit.label());
+        for value in &self.rho_values {
+            text.push_str(&format!(
+                "{}\t{:.4}\n",
+                value.excitation,
+                rho_unit.convert(value.rho)
+            ));
+        }
+        text
+    }
+
+    // levels restored with the session that still belong to the current residual
+    fn has_cached_levels(&self) -> bool {
+        !self.excitation_levels.is_empty()
+            && self
                 .resid_data
                 .as_ref()
-                .map_or("None", |data| &data.isotope)
+                .is_some_and(|data| data.isotope == self.levels_isotope)
+    }
+
+    // None until "Get Reaction" has looked up all four nuclei
+    // In inverse kinematics the target nucleus is the one moving, so it takes the projectile's place
+    pub fn nuclei(&self) -> Option<ReactionNuclei<'_>> {
+        let (mut target, mut projectile) =
+            (self.target_data.as_ref()?, self.projectile_data.as_ref()?);
+        if self.inverse_kinematics {
+            std::mem::swap(&mut target, &mut projectile);
+        }
+        Some(ReactionNuclei {
+            target,
+            projectile,
+            ejectile: self.ejectile_data.as_ref()?,
+            resid: self.resid_data.as_ref()?,
+        })
+    }
+
+    // Maximum ejectile angle (degrees) of the ground state when the kinematics are double valued,
+    // levels are plotted at their fast solution and vanish past the angle
+    pub fn double_valued(&self, beam_energy: f64) -> Option<f64> {
+        self.nuclei()?.max_ejectile_angle(0.0, beam_energy)
+    }
+
+    // names the particles whose masses are missing from the AMDC table
+    fn missing_masses_message(&self) -> String {
+        let particles = [
+            ("target", self.target_z, self.target_a, &self.target_data),
+            (
+                "projectile",
+                self.projectile_z,
+                self.projectile_a,
+                &self.projectile_data,
+            ),
+            (
+                "ejectile",
+                self.ejectile_z,
+                self.ejectile_a,
+                &self.ejectile_data,
+            ),
+            ("residual", self.resid_z, self.resid_a, &self.resid_data),
+        ];
+
+        let missing = particles
+            .iter()
+            .filter(|(_, _, _, data)| data.is_none())
+            .map(|(name, z, a, _)| format!("{} (Z = {}, A = {})", name, z, a))
+            .collect::<Vec<_>>();
+
+        format!("No mass found for the {}", missing.join(", "))
+    }
+
+    // Focal plane rho (cm) of a level at `excitation` MeV, None if any of the nuclear data is missing
+    pub fn rho_at(
+        &self,
+        excitation: f64,
+        beam_energy: f64,
+        magnetic_field: f64,
+        sps_angle: f64,
+    ) -> Option<f64> {
+        let nuclei = self.nuclei()?;
+        if nuclei.rho_problem(magnetic_field).is_some() {
+            return None;
+        }
+        Some(nuclei.rho(excitation, beam_energy, magnetic_field, sps_angle))
+    }
+
+    // (Q, threshold) in MeV of an endothermic reaction the beam can't drive, even to the ground state
+    pub fn below_threshold(&self, beam_energy: f64) -> Option<(f64, f64)> {
+        let nuclei = self.nuclei()?;
+        let (q_value, threshold) = (nuclei.q_value(), nuclei.threshold(0.0));
+        (q_value < 0.0 && beam_energy < threshold).then_some((q_value, threshold))
+    }
+
+    // inverts rho = Bρ / B for the level, None if that needs more than the SPS can reach
+    pub fn field_to_center_level(
+        &self,
+        excitation: f64,
+        beam_energy: f64,
+        sps_angle: f64,
+        rho: f64,
+    ) -> Option<f64> {
+        let field = self.nuclei()?.brho(excitation, beam_energy, sps_angle) / rho;
+        (field.is_finite() && field <= MAX_FIELD).then_some(field)
+    }
+
+    pub fn excitation_at_rho(
+        &self,
+        rho: f64,
+        beam_energy: f64,
+        magnetic_field: f64,
+        sps_angle: f64,
+    ) -> Option<f64> {
+        self.nuclei()?
+            .excitation_at_rho(rho, beam_energy, magnetic_field, sps_angle)
+    }
+
+    // Converts the (filtered) levels to rho at the given settings, errors are left in `calculation_error`.
+    // Only needs the nuclear data from `populate_reaction_data`, nothing from the app.
+    pub fn calculate_rho(
+        &mut self,
+        evaluation: MassEvaluation,
+        beam_energy: f64,
+        magnetic_field: f64,
+        sps_angle: f64,
+    ) {
+        self.rho_values.clear();
+
+        self.calculation_error = None;
+        self.apply_mass_overrides(evaluation);
+
+        let Some(nuclei) = self.nuclei() else {
+            let error = self.missing_masses_message();
+            log::error!("{}", error);
+            self.calculation_error = Some(error);
+            return;
+        };
+
+        // infinite rho would blow up the plot bounds
+        if let Some(problem) = nuclei.rho_problem(magnetic_field) {
+            log::error!("{}: {}", self.reaction_identifier, problem);
+            self.calculation_error = Some(problem.to_string());
+            return;
+        }
+
+        // stored on the reaction once nuclei, which borrows it, is done with
+        let threshold = nuclei.threshold(0.0);
+        if beam_energy < threshold {
+            let error = format!(
+                "Inaccessible, the beam is below the {:.3} MeV threshold",
+                threshold
+            );
+            log::error!("{}: {}", self.reaction_identifier, error);
+            self.threshold = Some(threshold);
+            self.calculation_error = Some(error);
+            return;
+        }
+
+        let reaction_identifier = format!(
+            "{}({},{}){}",
+            nuclei.target.isotope,
+            nuclei.projectile.isotope,
+            nuclei.ejectile.isotope,
+            nuclei.resid.isotope
+        );
+        info!("Reaction: {}", reaction_identifier);
+
+        // filtered out levels are dropped before any kinematics, heavy nuclei can have hundreds
+        let mut levels: Vec<Level> = self
+            .excitation_levels
+            .iter()
+            .filter(|level| {
+                level.energy >= self.level_filter_min && level.energy <= self.level_filter_max
+            })
+            .filter(|level| self.isomer.map_or(true, |energy| level.energy == energy))
+            .cloned()
+            .collect();
+
+        // manually added levels are never filtered out, copies of fetched ones are only drawn once
+        for (_, level) in self.additional_excitation_levels.iter() {
+            if !levels.iter().any(|fetched| fetched.energy == *level) {
+                levels.push(Level::new(*level));
+            }
+        }
+
+        // the web logger prints debug, a line per level would flood the console on every Calculate
+        log::debug!("{} excitation levels", levels.len());
+        log::trace!("Excitation levels: {:?}", levels);
+
+        // rho_problem was checked above, so this is rho_at without looking up the nuclei per level
+        let rho_at = |excitation: f64| -> f64 {
+            nuclei.rho(excitation, beam_energy, magnetic_field, sps_angle)
+        };
+
+        let mut rho_values = Vec::with_capacity(levels.len());
+        for level in levels {
+            let excitation = level.energy;
+            let brho = nuclei.brho(excitation, beam_energy, sps_angle);
+            let rho = brho / magnetic_field;
+            log::trace!("Excitation: {}, rho: {}", excitation, rho);
+
+            // past the maximum angle of two-valued kinematics the level can't reach the spectrograph
+            if !rho.is_finite() {
+                continue;
+            }
+
+            // rho decreases with excitation energy, so take half the spread of the two edges
+            let rho_uncertainty = level
+                .uncertainty
+                .map(|du| (rho_at(excitation - du) - rho_at(excitation + du)).abs() / 2.0);
+
+            let isomer = level.is_isomer();
+            rho_values.push(RhoValue {
+                excitation,
+                uncertainty: level.uncertainty,
+                jpi: level.jpi,
+                rho,
+                rho_uncertainty,
+                ejectile_energy: nuclei.ejectile_energy(excitation, beam_energy, sps_angle),
+                momentum: nuclei.momentum(excitation, beam_energy, sps_angle),
+                brho,
+                isomer,
+                half_life: level.half_life,
+                name: self.additional_level_name(excitation),
+            });
+        }
+
+        self.rho_values = rho_values;
+        self.threshold = Some(threshold);
+    }
+
+    // the Q-value follows from the residual mass, so a manual residual changes it too
+    fn populate_reaction_data(reaction: &mut Reaction, evaluation: MassEvaluation) {
+        if !reaction.manual_residual {
+            reaction.resid_z = reaction.target_z + reaction.projectile_z - reaction.ejectile_z;
+            reaction.resid_a = reaction.target_a + reaction.projectile_a - reaction.ejectile_a;
+        }
+
+        reaction.target_data = NuclearData::get_data(
+            reaction.target_z as u32,
+            reaction.target_a as u32,
+            evaluation,
+        );
+        reaction.projectile_data = NuclearData::get_data(
+            reaction.projectile_z as u32,
+            reaction.projectile_a as u32,
+            evaluation,
+        );
+        reaction.ejectile_data = NuclearData::get_data(
+            reaction.ejectile_z as u32,
+            reaction.ejectile_a as u32,
+            evaluation,
+        );
+        reaction.resid_data =
+            NuclearData::get_data(reaction.resid_z as u32, reaction.resid_a as u32, evaluation);
+
+        // written beam last, d(12C,p)13C is a 12C beam on a deuterium target
+        let (target, projectile) = if reaction.inverse_kinematics {
+            (&reaction.projectile_data, &reaction.target_data)
+        } else {
+            (&reaction.target_data, &reaction.projectile_data)
+        };
+        reaction.reaction_identifier = format!(
+            "{}({},{}){}",
+            target.as_ref().map_or("None", |data| &data.isotope),
+            projectile.as_ref().map_or("None", |data| &data.isotope),
+            reaction
+                .ejectile_data
+                .as_ref()
+                .map_or("None", |data| &data.isotope),
+            reaction
+                .resid_data
+                .as_ref()
+                .map_or("None", |data| &data.isotope)
+        );
+
+        info!("Reaction: {:?}", reaction);
+    }
+
+    // levels already saved for the same residual are reused unless `refresh` is set
+    fn fetch_excitation_levels(reaction: &mut Reaction, data_source: DataSource, refresh: bool) {
+        reaction.fetch_error = None;
+
+        let isotope = reaction
+            .resid_data
+            .as_ref()
+            .map_or("None", |data| &data.isotope);
+        if isotope == "None" {
+            log::error!(
+                "No isotope found for reaction: {}",
+                reaction.reaction_identifier
+            );
+            reaction.fetch_error = Some(format!(
+                "No isotope found for reaction: {}",
+                reaction.reaction_identifier
+            ));
+            return;
+        }
+
+        if !refresh && reaction.has_cached_levels() {
+            log::info!("Using saved excitation levels for {}", isotope);
+            return;
+        }
+
+        // NNDC can take a while, the app sends queued reactions off to the background fetch
+        if data_source == DataSource::Nndc && reaction.ensdf_file.is_none() {
+            reaction.levels_queued = true;
+            return;
+        }
+
+        match reaction.get_levels(isotope, data_source) {
+            Ok((levels, source)) => {
+                log::info!("Excitation levels for {}: {:?}", isotope, levels);
+                reaction.levels_isotope = isotope.to_string();
+                reaction.levels_source = source;
+                reaction.excitation_levels = levels;
+                reaction.levels_fetched = true;
+                // NNDC fetches are stamped as they arrive, this is a file or the bundled table
+                reaction.levels_fetched_at =
+                    if data_source == DataSource::Bundled && reaction.ensdf_file.is_none() {
+                        BUNDLED_LEVELS_RETRIEVED.to_string()
+                    } else {
+                        String::new()
+                    };
+            }
+            Err(error) => {
+                log::error!("No excitation levels found for {}: {}", isotope, error);
+                reaction.fetch_error = Some(error);
+            }
+        }
+    }
+
+    // the ejectile's adopted levels, the dataset and ENSDF file only apply to the residual
+    fn fetch_ejectile_levels(reaction: &mut Reaction, data_source: DataSource) {
+        reaction.ejectile_levels_error = None;
+
+        let Some(isotope) = reaction.ejectile_data.as_ref().map(|data| &data.isotope) else {
+            reaction.ejectile_levels_error = Some("Get Reaction first".to_string());
+            return;
+        };
+
+        if data_source == DataSource::Nndc {
+            reaction.ejectile_levels_queued = true;
+            return;
+        }
+
+        match data_source.get_levels(isotope, "") {
+            Ok(levels) => {
+                log::info!("Ejectile levels for {}: {:?}", isotope, levels);
+                reaction.ejectile_levels = levels;
+            }
+            Err(error) => {
+                log::error!("No ejectile levels found for {}: {}", isotope, error);
+                reaction.ejectile_levels_error = Some(error);
+            }
+        }
+    }
+}
+
+// Additional levels were saved as bare energies before they had names, those load unnamed
+fn named_levels<'de, D>(deserializer: D) -> Result<Vec<(String, f64)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum SavedLevel {
+        Named(String, f64),
+        Energy(f64),
+    }
+
+    let levels = <Vec<SavedLevel> as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(levels
+        .into_iter()
+        .map(|level| match level {
+            SavedLevel::Named(name, energy) => (name, energy),
+            SavedLevel::Energy(energy) => (String::new(), energy),
+        })
+        .collect())
+}
+
+// Levels were saved as bare energies before they carried Jπ, those load without one
+fn saved_levels<'de, D>(deserializer: D) -> Result<Vec<Level>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum SavedLevel {
+        Energy(f64),
+        Level(Level),
+    }
+
+    let levels = <Vec<SavedLevel> as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(levels
+        .into_iter()
+        .map(|level| match level {
+            SavedLevel::Energy(energy) => Level::new(energy),
+            SavedLevel::Level(level) => level,
+        })
+        .collect())
+}
+
+// Rho values were saved as (excitation, rho) pairs before they carried the rest of the level.
+// The pair is tried first, a struct would also take the two numbers as its first two fields.
+fn saved_rho_values<'de, D>(deserializer: D) -> Result<Vec<RhoValue>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum SavedRhoValue {
+        Pair(f64, f64),
+        Value(RhoValue),
+    }
+
+    let values = <Vec<SavedRhoValue> as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .map(|value| match value {
+            SavedRhoValue::Pair(excitation, rho) => RhoValue {
+                excitation,
+                rho,
+                ..Default::default()
+            },
+            SavedRhoValue::Value(value) => value,
+        })
+        .collect())
+}
+
+// field (kG) and angle (degrees) that fit the most must-see levels on the focal plane
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BestSetting {
+    field: f64,
+    angle: f64,
+    inside: usize,
+    total: usize,
+}
+
+// (isotope, NNDC dataset, levels), the dataset is empty for the adopted levels
+type FetchedLevels = (String, String, Result<Vec<Level>, String>);
+
+// the reaction a secondary is grouped under, only one level deep so a parent that is itself
+// secondary (or missing after a remove) leaves the reaction on its own
+fn parent_of(reactions: &[Reaction], index: usize) -> Option<usize> {
+    reactions[index].parent.filter(|parent| {
+        *parent != index
+            && reactions
+                .get(*parent)
+                .map_or(false, |parent| parent.parent.is_none())
+    })
+}
+
+// the first earlier reaction with the same target, projectile and ejectile, their bars would overlap
+fn duplicate_of(reactions: &[Reaction], index: usize) -> Option<usize> {
+    let channel = |reaction: &Reaction| {
+        (
+            (reaction.target_z, reaction.target_a),
+            (reaction.projectile_z, reaction.projectile_a),
+            (reaction.ejectile_z, reaction.ejectile_a),
+            reaction.inverse_kinematics,
+        )
+    };
+    let reaction = &reactions[index];
+    // new reactions start out all zero
+    if reaction.target_a == 0 || reaction.copy {
+        return None;
+    }
+    reactions[..index]
+        .iter()
+        .position(|other| channel(other) == channel(reaction))
+}
+
+// (index, secondary) in list and stacking order, each parent followed by its secondaries
+fn reaction_order(reactions: &[Reaction]) -> Vec<(usize, bool)> {
+    let mut order = Vec::with_capacity(reactions.len());
+    for index in (0..reactions.len()).filter(|index| parent_of(reactions, *index).is_none()) {
+        order.push((index, false));
+        for child in 0..reactions.len() {
+            if parent_of(reactions, child) == Some(index) {
+                order.push((child, true));
+            }
+        }
+    }
+    order
+}
+
+// Numbered like the reaction list, so two reactions with the same identifier get their own legend
+// entry. The visibility lives on the reaction, so renumbering after a removal or move keeps it.
+fn legend_name(index: usize, reaction: &Reaction) -> String {
+    format!("{}: {}", index, reaction.reaction_identifier)
+}
+
+// edits a field stored in kG in the display unit, `speed` is in kG per pixel
+fn field_drag_value(field: &mut f64, unit: FieldUnit, speed: f64) -> egui::DragValue<'_> {
+    egui::DragValue::from_get_set(move |value| {
+        if let Some(value) = value {
+            *field = unit.to_kilogauss(value);
+        }
+        unit.convert(*field)
+    })
+    .suffix(format!(" {}", unit.label()))
+    .speed(unit.convert(speed))
+    .clamp_range(0.0..=unit.convert(MAX_FIELD))
+}
+
+// small boxed text beside the cursor, over the plot
+fn crosshair_readout(ui: &egui::Ui, pointer: egui::Pos2, text: String) {
+    let painter = ui.painter();
+    let galley = painter.layout_no_wrap(
+        text,
+        egui::FontId::monospace(13.0),
+        ui.visuals().strong_text_color(),
+    );
+    let position = pointer + egui::vec2(14.0, 14.0);
+    let frame = egui::Rect::from_min_size(position, galley.size()).expand(4.0);
+    painter.rect_filled(frame, 3.0, ui.visuals().extreme_bg_color);
+    painter.rect_stroke(frame, 3.0, ui.visuals().window_stroke());
+    painter.galley(position, galley, Color32::PLACEHOLDER);
+}
+
+// NuDat page of a named dataset, built by the getter that reads it
+#[cfg(not(target_arch = "wasm32"))]
+fn dataset_page(isotope: &str, dataset: &str) -> String {
+    nndc_excitation_level_getter::excitation_fetcher::dataset_url(isotope, dataset)
+        .map_or_else(|_| nndc_url(isotope), |url| url.to_string())
+}
+
+// named datasets are only read natively
+#[cfg(target_arch = "wasm32")]
+fn dataset_page(isotope: &str, _dataset: &str) -> String {
+    nndc_url(isotope)
+}
+
+// tick labels for a rho axis, the plot coordinates stay in cm
+fn rho_tick_formatter(unit: RhoUnit) -> impl Fn(GridMark, usize, &RangeInclusive<f64>) -> String {
+    move |mark, _, _| {
+        let step = unit.convert(mark.step_size);
+        let decimals = (-step.log10()).ceil().max(0.0) as usize;
+        format!("{:.*}", decimals, unit.convert(mark.value))
+    }
+}
+
+// edits a rho stored in cm in the display unit
+fn rho_drag_value(rho: &mut f64, unit: RhoUnit) -> egui::DragValue<'_> {
+    egui::DragValue::from_get_set(move |value| {
+        if let Some(value) = value {
+            *rho = unit.to_centimeter(value);
+        }
+        unit.convert(*rho)
+    })
+    .suffix(format!(" {}", unit.label()))
+    .speed(unit.convert(1.0))
+    .min_decimals(if unit == RhoUnit::Meter { 3 } else { 0 })
+    .clamp_range(0.0..=f64::MAX)
+}
+
+// Which of the key numbers the LaTeX table has, the reaction column is always there
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct LatexColumns {
+    pub q_value: bool,
+    pub ground_state_rho: bool,
+    pub max_excitation: bool,
+}
+
+impl Default for LatexColumns {
+    fn default() -> Self {
+        Self {
+            q_value: true,
+            ground_state_rho: true,
+            max_excitation: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SPSPlotApp {
+    sps_angle: f64,
+    beam_energy: f64,
+    magnetic_field: f64,
+    rho_min: f64,
+    rho_max: f64,
+    rho_resolution: f64, // cm, 0 draws every level separately
+    reactions: Vec<Reaction>,
+    side_panel: bool,
+    // reaction whose levels the side panel shows, None puts every reaction side by side
+    side_panel_reaction: Option<usize>,
+    window: bool,
+    dark_mode: bool,
+    show_peak_labels: bool,
+    show_crosshair: bool, // follows the cursor with the rho and excitation energy under it
+    ground_state_only: bool,
+    energy_decimals: usize, // MeV decimals shown, the stored energies aren't rounded
+    show_momentum: bool,    // adds p and Bρ columns to the CSV export
+    latex_columns: LatexColumns,
+    field_unit: FieldUnit, // display only, the field is stored in kG
+    results_panel: bool,
+    yields: YieldSettings,
+    tandem: TandemSettings,
+    #[serde(skip)]
+    best_setting: Option<BestSetting>, // result of the last field and angle search
+    outside_acceptance: OutsideAcceptance, // how levels off the focal plane are drawn
+    palette: ColorPalette,
+    custom_palette: Vec<Color32>,
+    results_sort: ResultsColumn,
+    results_ascending: bool,
+    #[serde(skip)]
+    highlighted_level: Option<(usize, f64)>, // reaction index and excitation picked in the table
+    rho_unit: RhoUnit, // display only, rho is stored in cm
+    bar_height: f64,
+    bar_width: f64,        // cm
+    reaction_spacing: f64, // distance between each reaction's baseline
+    color_by_spin: bool,
+    beam_energy_step: f64, // MeV per arrow key press
+    field_step: f64,       // kG per arrow key press
+    #[serde(skip)]
+    last_step_time: Option<f64>,
+    lock_bounds: bool, // pins the view to the acceptance, unlock to zoom and pan
+    #[serde(skip)]
+    auto_fit_requested: bool,
+    show_overview: bool, // strip next to the plot with every reaction row, for tall stacks
+    #[serde(skip)]
+    overview_jump: Option<f64>, // stacking offset the overview was clicked or dragged to
+    energy_axis_reaction: Option<usize>,
+    acceptance_color: Color32,
+    plot_orientation: PlotOrientation,
+    plot_mode: PlotMode,
+    scan_reaction: usize,
+    scan_excitation: f64,      // MeV
+    scan_beam_energy_min: f64, // MeV
+    scan_beam_energy_max: f64, // MeV
+    show_search: bool,
+    search_rho: f64,        // cm
+    search_tolerance: f64,  // cm
+    search_by_energy: bool, // the peak is given as an energy of search_reaction instead
+    search_energy: f64,     // MeV
+    search_reaction: usize,
+    field_settings: Vec<f64>, // kG, compared against magnetic_field on the plot
+    data_source: DataSource,
+    mass_evaluation: MassEvaluation,
+    auto_calc: bool, // recalculate shortly after the settings or a reaction change
+    #[serde(skip)]
+    auto_calc_inputs: String, // what the last calculation was for, see `calculation_inputs`
+    #[serde(skip)]
+    auto_calc_changed: Option<f64>, // time the inputs last changed, None once calculated
+    ame2020_path: Option<std::path::PathBuf>, // reloaded on start up
+    #[serde(skip)]
+    ame2020_loads: usize, // counts the AME2020 tables read, a reload changes the masses
+    levels_csv_path: Option<std::path::PathBuf>, // getter output used over the bundled levels, reloaded on start up
+    #[serde(skip)]
+    fetched_levels: Arc<Mutex<Vec<FetchedLevels>>>,
+    #[serde(skip)]
+    pending_fetches: Vec<(String, String)>, // (isotope, dataset) sent to the background fetch
+    #[serde(skip)]
+    fetch_total: usize, // isotopes requested since nothing was pending
+    #[serde(skip)]
+    confirm_clear_reactions: bool,
+    #[serde(skip)]
+    confirm_remove_reaction: Option<usize>,
+    #[serde(skip)]
+    // the last removed reaction or the whole cleared list, with the index they were at, for undo
+    removed_reactions: Option<(usize, Vec<Reaction>)>,
+    #[serde(skip)]
+    file_message: Option<String>,
+    // reactions of a second project drawn muted over the plot, with its name and settings
+    #[serde(skip)]
+    comparison: Vec<Reaction>,
+    #[serde(skip)]
+    comparison_label: String,
+    #[serde(alias = "png_scale")]
+    png_resize: f32, // the screenshot is resampled, it is captured at screen resolution
+    #[serde(skip)]
+    plot_rect: Option<egui::Rect>,
+    #[serde(skip)]
+    png_export_requested: bool,
+    spectrum: Vec<[f64; 2]>, // (channel, counts)
+    show_spectrum: bool,
+    spectrum_color: Color32,
+    spectrum_log: bool,
+    subtract_background: bool,
+    background_window: usize,          // channels
+    calibration_points: [[f64; 2]; 2], // (channel, rho)
+    slope: f64,                        // cm/channel
+    offset: f64,                       // cm
+    quadratic: f64,                    // cm/channel²
+    fit_points: Vec<[f64; 2]>,         // (channel, excitation MeV)
+    fit_reaction: usize,
+    fit_quadratic: bool,
+    #[serde(skip)]
+    fit_residuals: Vec<f64>, // keV
+    #[serde(skip)]
+    fit_error: Option<String>,
+}
+
+impl Default for SPSPlotApp {
+    fn default() -> Self {
+        Self {
+            sps_angle: 35.0,
+            beam_energy: 16.0,
+            magnetic_field: 8.7,
+            rho_min: 69.0,
+            rho_max: 87.0,
+            rho_resolution: 0.0,
+            reactions: Vec::new(),
+            side_panel: false,
+            side_panel_reaction: Some(0),
+            window: false,
+            dark_mode: true,
+            show_peak_labels: false,
+            show_crosshair: false,
+            ground_state_only: false,
+            energy_decimals: 3,
+            show_momentum: false,
+            latex_columns: LatexColumns::default(),
+            field_unit: FieldUnit::Kilogauss,
+            results_panel: false,
+            yields: YieldSettings::default(),
+            tandem: TandemSettings::default(),
+            best_setting: None,
+            outside_acceptance: OutsideAcceptance::Show,
+            palette: ColorPalette::Garnet,
+            custom_palette: REACTION_COLORS.to_vec(),
+            results_sort: ResultsColumn::Reaction,
+            results_ascending: true,
+            highlighted_level: None,
+            rho_unit: RhoUnit::Centimeter,
+            bar_height: 0.5,
+            bar_width: 0.01,
+            reaction_spacing: 1.0,
+            color_by_spin: false,
+            beam_energy_step: 0.1,
+            field_step: 0.01,
+            last_step_time: None,
+            lock_bounds: true,
+            auto_fit_requested: false,
+            show_overview: false,
+            overview_jump: None,
+            energy_axis_reaction: None,
+            acceptance_color: Color32::from_rgba_unmultiplied(255, 0, 0, 20),
+            plot_orientation: PlotOrientation::default(),
+            plot_mode: PlotMode::default(),
+            scan_reaction: 0,
+            scan_excitation: 0.0,
+            scan_beam_energy_min: 5.0,
+            scan_beam_energy_max: 30.0,
+            show_search: false,
+            search_rho: 78.0,
+            search_tolerance: 0.5,
+            search_by_energy: false,
+            search_energy: 0.0,
+            search_reaction: 0,
+            field_settings: Vec::new(),
+            data_source: DataSource::default(),
+            mass_evaluation: MassEvaluation::default(),
+            auto_calc: false,
+            auto_calc_inputs: String::new(),
+            auto_calc_changed: None,
+            ame2020_path: None,
+            ame2020_loads: 0,
+            levels_csv_path: None,
+            fetched_levels: Arc::new(Mutex::new(Vec::new())),
+            pending_fetches: Vec::new(),
+            fetch_total: 0,
+            confirm_clear_reactions: false,
+            confirm_remove_reaction: None,
+            removed_reactions: None,
+            file_message: None,
+            comparison: Vec::new(),
+            comparison_label: String::new(),
+            png_resize: 2.0,
+            plot_rect: None,
+            png_export_requested: false,
+            spectrum: Vec::new(),
+            show_spectrum: true,
+            spectrum_color: Color32::GRAY,
+            spectrum_log: false,
+            subtract_background: false,
+            background_window: 50,
+            calibration_points: [[0.0, 69.0], [4096.0, 87.0]],
+            slope: (87.0 - 69.0) / 4096.0,
+            offset: 69.0,
+            quadratic: 0.0,
+            fit_points: Vec::new(),
+            fit_reaction: 0,
+            fit_quadratic: false,
+            fit_residuals: Vec::new(),
+            fit_error: None,
+        }
+    }
+}
+
+impl SPSPlotApp {
+    pub fn new(cc: &eframe::CreationContext<'_>, window: bool) -> Self {
+        let mut app = Self {
+            sps_angle: 35.0,     // degree
+            beam_energy: 16.0,   // MeV
+            magnetic_field: 8.7, // kG
+            rho_min: 69.0,
+            rho_max: 87.0,
+            rho_resolution: 0.0,
+            reactions: Vec::new(),
+            side_panel: false,
+            side_panel_reaction: Some(0),
+            window,
+            dark_mode: true,
+            show_peak_labels: false,
+            show_crosshair: false,
+            ground_state_only: false,
+            energy_decimals: 3,
+            show_momentum: false,
+            latex_columns: LatexColumns::default(),
+            field_unit: FieldUnit::Kilogauss,
+            results_panel: false,
+            yields: YieldSettings::default(),
+            tandem: TandemSettings::default(),
+            best_setting: None,
+            outside_acceptance: OutsideAcceptance::Show,
+            palette: ColorPalette::Garnet,
+            custom_palette: REACTION_COLORS.to_vec(),
+            results_sort: ResultsColumn::Reaction,
+            results_ascending: true,
+            highlighted_level: None,
+            rho_unit: RhoUnit::Centimeter,
+            bar_height: 0.5,
+            bar_width: 0.01,
+            reaction_spacing: 1.0,
+            color_by_spin: false,
+            beam_energy_step: 0.1,
+            field_step: 0.01,
+            last_step_time: None,
+            lock_bounds: true,
+            auto_fit_requested: false,
+            show_overview: false,
+            overview_jump: None,
+            energy_axis_reaction: None,
+            acceptance_color: Color32::from_rgba_unmultiplied(255, 0, 0, 20),
+            plot_orientation: PlotOrientation::default(),
+            plot_mode: PlotMode::default(),
+            scan_reaction: 0,
+            scan_excitation: 0.0,
+            scan_beam_energy_min: 5.0,
+            scan_beam_energy_max: 30.0,
+            show_search: false,
+            search_rho: 78.0,
+            search_tolerance: 0.5,
+            search_by_energy: false,
+            search_energy: 0.0,
+            search_reaction: 0,
+            field_settings: Vec::new(),
+            data_source: DataSource::default(),
+            mass_evaluation: MassEvaluation::default(),
+            auto_calc: false,
+            auto_calc_inputs: String::new(),
+            auto_calc_changed: None,
+            ame2020_path: None,
+            ame2020_loads: 0,
+            levels_csv_path: None,
+            fetched_levels: Arc::new(Mutex::new(Vec::new())),
+            pending_fetches: Vec::new(),
+            fetch_total: 0,
+            confirm_clear_reactions: false,
+            confirm_remove_reaction: None,
+            removed_reactions: None,
+            file_message: None,
+            comparison: Vec::new(),
+            comparison_label: String::new(),
+            png_resize: 2.0,
+            plot_rect: None,
+            png_export_requested: false,
+            spectrum: Vec::new(),
+            show_spectrum: true,
+            spectrum_color: Color32::GRAY,
+            spectrum_log: false,
+            subtract_background: false,
+            background_window: 50,
+            calibration_points: [[0.0, 69.0], [4096.0, 87.0]],
+            slope: (87.0 - 69.0) / 4096.0,
+            offset: 69.0,
+            quadratic: 0.0,
+            fit_points: Vec::new(),
+            fit_reaction: 0,
+            fit_quadratic: false,
+            fit_residuals: Vec::new(),
+            fit_error: None,
+        };
+
+        if let Some(storage) = cc.storage {
+            app = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+        }
+
+        // a project saved natively may ask for NNDC, which isn't available on the web
+        #[cfg(target_arch = "wasm32")]
+        {
+            app.data_source = DataSource::Bundled;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = app.ame2020_path.clone() {
+            app.read_ame2020(&path);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = app.levels_csv_path.clone() {
+            app.read_levels_csv(&path);
+        }
+
+        // the theme switch only changes the context, so restore the saved choice
+        cc.egui_ctx.set_visuals(if app.dark_mode {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        });
+
+        app
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_project(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_file_name("sps_plot_project.json")
+            .save_file()
+        else {
+            return;
+        };
+
+        self.file_message = super::project::save_project(self, &path).err();
+        if let Some(error) = &self.file_message {
+            log::error!("{}", error);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_project(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match super::project::load_project(&path) {
+            Ok(app) => self.open_project(app),
+            Err(error) => {
+                log::error!("{}", error);
+                self.file_message = Some(error);
+            }
+        }
+    }
+
+    // Takes over a loaded project. The AME2020 masses and imported levels are process wide, so
+    // they're re-read from its paths, and NNDC fetches still on their way keep being received.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_project(&mut self, app: SPSPlotApp) {
+        let window = self.window;
+        let fetched_levels = Arc::clone(&self.fetched_levels);
+        let pending_fetches = std::mem::take(&mut self.pending_fetches);
+        let fetch_total = self.fetch_total;
+
+        *self = app;
+        self.window = window;
+        self.fetched_levels = fetched_levels;
+        self.pending_fetches = pending_fetches;
+        self.fetch_total = fetch_total;
+
+        if let Some(path) = self.ame2020_path.clone() {
+            self.read_ame2020(&path);
+        }
+        match self.levels_csv_path.clone() {
+            Some(path) => self.read_levels_csv(&path),
+            None => super::excitation_levels_nndc::set_imported_levels(None),
+        }
+    }
+
+    // keeps the current project and only takes the other one's calculated levels
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_comparison(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match super::project::load_project(&path) {
+            Ok(other) => {
+                let name = path
+                    .file_stem()
+                    .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+                self.comparison_label = format!(
+                    "{}: {:.2} MeV, {:.3} kG, {:.1}°",
+                    name, other.beam_energy, other.magnetic_field, other.sps_angle
+                );
+                self.comparison = other.reactions;
+            }
+            Err(error) => {
+                log::error!("{}", error);
+                self.file_message = Some(error);
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("sps_plot_rho_values.csv")
+            .save_file()
+        else {
+            return;
+        };
+
+        let csv = super::export::rho_values_csv(&self.reactions, self.show_momentum);
+        self.file_message = std::fs::write(&path, csv)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+            .err();
+        if let Some(error) = &self.file_message {
+            log::error!("{}", error);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_latex(&mut self) {
+        use super::export::{reactions_latex, LatexTable};
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("LaTeX", &["tex"])
+            .set_file_name("sps_plot_reactions.tex")
+            .save_file()
+        else {
+            return;
+        };
+
+        let latex = reactions_latex(&LatexTable {
+            reactions: &self.reactions,
+            columns: self.latex_columns,
+            beam_energy: self.beam_energy,
+            magnetic_field: self.magnetic_field,
+            sps_angle: self.sps_angle,
+            rho_min: self.rho_min,
+        });
+        self.file_message = std::fs::write(&path, latex)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+            .err();
+        if let Some(error) = &self.file_message {
+            log::error!("{}", error);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_ame2020(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("AME2020", &["mas20", "txt"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        self.read_ame2020(&path);
+    }
+
+    // Recalculates a loaded project without any egui context, returns the rho table as CSV
+    // and the problems the UI would otherwise show
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn rho_table(&mut self) -> (String, Vec<String>) {
+        let mut warnings = Vec::new();
+
+        if let Some(path) = self.ame2020_path.clone() {
+            self.read_ame2020(&path);
+            warnings.extend(self.file_message.take());
+        }
+        if let Some(path) = self.levels_csv_path.clone() {
+            self.read_levels_csv(&path);
+            warnings.extend(self.file_message.take());
+        }
+
+        // hand written projects may only give Z and A
+        for reaction in &mut self.reactions {
+            Reaction::populate_reaction_data(reaction, self.mass_evaluation);
+        }
+        self.calculate_rho_for_all_reactions();
+        for reaction in &self.reactions {
+            if let Some(error) = &reaction.calculation_error {
+                warnings.push(format!("{}: {}", reaction.reaction_identifier, error));
+            }
+        }
+
+        let csv = super::export::rho_values_csv(&self.reactions, self.show_momentum);
+        (csv, warnings)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_ame2020(&mut self, path: &std::path::Path) {
+        let result = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            .and_then(|contents| super::nuclear_data_ame2020::load(&contents));
+
+        match result {
+            Ok(count) => {
+                log::info!("Loaded {} AME2020 masses from {:?}", count, path);
+                self.ame2020_path = Some(path.to_path_buf());
+                self.ame2020_loads += 1;
+                self.file_message = None;
+            }
+            Err(error) => {
+                log::error!("{}", error);
+                self.ame2020_path = None;
+                self.file_message = Some(error);
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_levels_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("excitation_levels.csv")
+            .pick_file()
+        else {
+            return;
+        };
+
+        self.read_levels_csv(&path);
+    }
+
+    // the excitation_levels.csv written by nndc_excitation_level_getter, rows it can't use are reported
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_levels_csv(&mut self, path: &std::path::Path) {
+        use nndc_excitation_level_getter::excitation_fetcher::read_levels_csv;
+
+        let result = read_levels_csv(&path.to_string_lossy())
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            .and_then(super::excitation_levels_nndc::import_levels);
+
+        match result {
+            Ok((imported, skipped)) => {
+                log::info!(
+                    "Imported the levels of {} isotopes from {:?}",
+                    imported.len(),
+                    path
+                );
+                super::excitation_levels_nndc::set_imported_levels(Some(imported));
+                self.levels_csv_path = Some(path.to_path_buf());
+                self.file_message = (!skipped.is_empty()).then(|| {
+                    format!(
+                        "Skipped {} rows of {} not named like 12C: {}",
+                        skipped.len(),
+                        path.display(),
+                        skipped.join(", ")
+                    )
+                });
+            }
+            Err(error) => {
+                log::error!("{}", error);
+                self.levels_csv_path = None;
+                self.file_message = Some(error);
+            }
+        }
+    }
+
+    // called once the screenshot requested by "Export PNG" arrives, crops it to the plot
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_png(&mut self, ctx: &egui::Context) {
+        if !self.png_export_requested {
+            return;
+        }
+
+        let screenshot = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+
+        let (Some(screenshot), Some(plot_rect)) = (screenshot, self.plot_rect) else {
+            return;
+        };
+        self.png_export_requested = false;
+
+        let image = screenshot.region(&plot_rect, Some(ctx.pixels_per_point()));
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .set_file_name("sps_plot.png")
+            .save_file()
+        else {
+            return;
+        };
+
+        self.file_message = super::export::save_png(&image, self.png_resize, &path).err();
+        if let Some(error) = &self.file_message {
+            log::error!("{}", error);
+        }
+    }
+
+    // redrawn from the rho values with rho along x, whatever the on screen orientation
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_svg(&mut self) {
+        use super::export::{plot_svg, SvgPlot};
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("SVG", &["svg"])
+            .set_file_name("sps_plot.svg")
+            .save_file()
+        else {
+            return;
+        };
+
+        let svg = plot_svg(&SvgPlot {
+            reactions: &self.reactions,
+            rows: &self.reaction_rows(),
+            rho_range: (self.rho_min - 5.0, self.rho_max + 5.0),
+            acceptance: (self.rho_min, self.rho_max),
+            acceptance_color: self.acceptance_color,
+            bar_height: self.bar_height,
+            reaction_spacing: self.reaction_spacing,
+        });
+        self.file_message = std::fs::write(&path, svg)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+            .err();
+        if let Some(error) = &self.file_message {
+            log::error!("{}", error);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_spectrum(&mut self) {
+        use super::spectrum::parse_spectrum_csv;
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv", "txt"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let (bins, skipped) = parse_spectrum_csv(&contents);
+                if skipped > 0 {
+                    log::warn!("Skipped {} malformed lines in {:?}", skipped, path);
+                }
+
+                self.file_message = if bins.is_empty() {
+                    Some(format!("No bins found in {}", path.display()))
+                } else {
+                    None
+                };
+                self.spectrum = bins;
+                self.show_spectrum = true;
+            }
+            Err(e) => {
+                log::error!("Failed to read {:?}: {}", path, e);
+                self.file_message = Some(format!("Failed to read {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    // linear channel -> rho calibration through the two user entered points
+    fn calibrate(&mut self) {
+        let [[channel_1, rho_1], [channel_2, rho_2]] = self.calibration_points;
+        if channel_1 == channel_2 {
+            self.file_message = Some("Calibration points need different channels".to_string());
+            return;
+        }
+
+        self.slope = (rho_2 - rho_1) / (channel_2 - channel_1);
+        self.offset = rho_1 - self.slope * channel_1;
+        self.quadratic = 0.0;
+        self.file_message = None;
+    }
+
+    fn channel_to_rho(&self, channel: f64) -> f64 {
+        self.offset + self.slope * channel + self.quadratic * channel * channel
+    }
+
+    // Fits channel -> rho from (channel, known excitation) pairs, with rho taken from the
+    // chosen reaction at the current settings. Residuals are reported in keV of excitation.
+    fn fit_energy_calibration(&mut self) {
+        self.fit_residuals.clear();
+        self.fit_error = None;
+
+        let Some(reaction) = self.reactions.get(self.fit_reaction) else {
+            self.fit_error = Some("Pick the reaction the peaks belong to".to_string());
+            return;
+        };
+
+        let mut points = Vec::new();
+        for [channel, excitation] in &self.fit_points {
+            match reaction.rho_at(
+                *excitation,
+                self.beam_energy,
+                self.magnetic_field,
+                self.sps_angle,
+            ) {
+                Some(rho) if rho.is_finite() => points.push([*channel, rho]),
+                _ => {
+                    self.fit_error = Some(format!(
+                        "No rho for {:.*} MeV, Get Reaction first",
+                        self.energy_decimals, excitation
+                    ));
+                    return;
+                }
+            }
+        }
+
+        let degree = if self.fit_quadratic { 2 } else { 1 };
+        let Some(coefficients) = fit_polynomial(&points, degree) else {
+            self.fit_error = Some(format!(
+                "Need at least {} points with different channels",
+                degree + 1
+            ));
+            return;
+        };
+
+        self.offset = coefficients[0];
+        self.slope = coefficients[1];
+        self.quadratic = coefficients.get(2).copied().unwrap_or(0.0);
+
+        let reaction = &self.reactions[self.fit_reaction];
+        self.fit_residuals = self
+            .fit_points
+            .iter()
+            .map(|[channel, excitation]| {
+                reaction
+                    .excitation_at_rho(
+                        self.channel_to_rho(*channel),
+                        self.beam_energy,
+                        self.magnetic_field,
+                        self.sps_angle,
+                    )
+                    .map_or(f64::NAN, |fitted| (fitted - excitation) * 1000.0)
+            })
+            .collect();
+    }
+
+    fn energy_calibration_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Energy Fit: ").on_hover_text(
+                "Fit the channel to rho calibration from peaks of known excitation energy",
+            );
+
+            let selected = self
+                .reactions
+                .get(self.fit_reaction)
+                .map_or("None".to_string(), |reaction| {
+                    reaction.reaction_identifier.clone()
+                });
+            egui::ComboBox::from_id_source("sps_plot_fit_reaction")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (index, reaction) in self.reactions.iter().enumerate() {
+                        ui.selectable_value(
+                            &mut self.fit_reaction,
+                            index,
+                            format!("{}: {}", index, reaction.reaction_identifier),
+                        );
+                    }
+                });
+
+            let mut to_remove: Option<usize> = None;
+            for (index, [channel, excitation]) in self.fit_points.iter_mut().enumerate() {
+                ui.add(egui::DragValue::new(channel).prefix("Channel: "));
+                ui.add(
+                    egui::DragValue::new(excitation)
+                        .prefix("E: ")
+                        .suffix(" MeV")
+                        .speed(0.01),
+                );
+                if let Some(residual) = self.fit_residuals.get(index) {
+                    ui.label(format!("({:+.1} keV)", residual));
+                }
+                if ui.small_button("x").clicked() {
+                    to_remove = Some(index);
+                }
+            }
+            if let Some(index) = to_remove {
+                self.fit_points.remove(index);
+                self.fit_residuals.clear();
+            }
+
+            if ui.button("+").on_hover_text("Add a peak").clicked() {
+                self.fit_points.push([0.0, 0.0]);
+                self.fit_residuals.clear();
+            }
+
+            ui.checkbox(&mut self.fit_quadratic, "Quadratic");
+
+            if ui.button("Fit").clicked() {
+                self.fit_energy_calibration();
+            }
+
+            if let Some(error) = &self.fit_error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+    }
+
+    fn scan_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("View: ");
+            ui.radio_value(&mut self.plot_mode, PlotMode::FocalPlane, "Focal Plane");
+            ui.radio_value(&mut self.plot_mode, PlotMode::AngleScan, "Angle Scan")
+                .on_hover_text(format!(
+                    "Rho of a single level as the SPS angle is swept from 0° to {}°",
+                    MAX_SPS_ANGLE
+                ));
+            ui.radio_value(
+                &mut self.plot_mode,
+                PlotMode::BeamEnergyScan,
+                "Beam Energy Scan",
+            )
+            .on_hover_text("Rho of a single level as the beam energy is swept");
+
+            if self.plot_mode == PlotMode::FocalPlane {
+                return;
+            }
+
+            ui.separator();
+
+            let selected = self
+                .reactions
+                .get(self.scan_reaction)
+                .map_or("None".to_string(), |reaction| {
+                    reaction.reaction_identifier.clone()
+                });
+            egui::ComboBox::from_label("Reaction")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (index, reaction) in self.reactions.iter().enumerate() {
+                        ui.selectable_value(
+                            &mut self.scan_reaction,
+                            index,
+                            format!("{}: {}", index, reaction.reaction_identifier),
+                        );
+                    }
+                });
+
+            ui.add(
+                egui::DragValue::new(&mut self.scan_excitation)
+                    .prefix("Level: ")
+                    .suffix(" MeV")
+                    .speed(0.01),
+            );
+
+            if self.plot_mode == PlotMode::BeamEnergyScan {
+                ui.add(
+                    egui::DragValue::new(&mut self.scan_beam_energy_min)
+                        .prefix("Beam: ")
+                        .suffix(" MeV")
+                        .clamp_range(0.0..=self.scan_beam_energy_max),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.scan_beam_energy_max)
+                        .prefix("to ")
+                        .suffix(" MeV")
+                        .clamp_range(self.scan_beam_energy_min..=f64::MAX),
+                );
+            }
+
+            if let Some(reaction) = self.reactions.get(self.scan_reaction) {
+                egui::ComboBox::from_id_source("sps_plot_scan_level")
+                    .selected_text("Pick")
+                    .show_ui(ui, |ui| {
+                        let levels = reaction
+                            .excitation_levels
+                            .iter()
+                            .map(|level| level.energy)
+                            .chain(
+                                reaction
+                                    .additional_excitation_levels
+                                    .iter()
+                                    .map(|(_, energy)| *energy),
+                            );
+                        for energy in levels {
+                            ui.selectable_value(
+                                &mut self.scan_excitation,
+                                energy,
+                                format!("{:.*} MeV", self.energy_decimals, energy),
+                            );
+                        }
+                    });
+            }
+        });
+    }
+
+    // Turning it on or editing a value sets the beam energy, which can still be stepped, scanned
+    // or loaded from there
+    fn tandem_ui(&mut self, ui: &mut egui::Ui) {
+        let toggled = ui
+            .checkbox(&mut self.tandem.enabled, "Tandem")
+            .on_hover_text("Set the beam energy from the terminal voltage and charge state")
+            .changed();
+
+        if !self.tandem.enabled {
+            return;
+        }
+
+        let tandem = &mut self.tandem;
+        let mut changed = ui
+            .add(
+                egui::DragValue::new(&mut tandem.terminal_voltage)
+                    .prefix("Terminal: ")
+                    .suffix(" MV")
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::MAX),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut tandem.charge_state)
+                    .prefix("q: ")
+                    .clamp_range(1..=u32::MAX),
+            )
+            .on_hover_text("Charge state after the terminal stripper")
+            .changed();
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut tandem.injection_energy)
+                    .prefix("Injection: ")
+                    .suffix(" keV")
+                    .clamp_range(0.0..=f64::MAX),
+            )
+            .on_hover_text("Energy of the negative ions from the source")
+            .changed();
+
+        let beam_energy = tandem.beam_energy();
+        ui.label(format!("= {:.3} MeV", beam_energy))
+            .on_hover_text("E = E_inj + (1 + q) V");
+        if changed || toggled {
+            self.beam_energy = beam_energy;
+            self.calculate_rho_for_all_reactions();
+        }
+    }
+
+    fn yield_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.yields.enabled, "Yield Estimate")
+                .on_hover_text(
+                    "Scale the bars by the expected counts. The normalization is rough, compare peak heights only",
+                );
+
+            if !self.yields.enabled {
+                return;
+            }
+
+            let settings = &mut self.yields;
+            ui.add(
+                egui::DragValue::new(&mut settings.beam_current)
+                    .prefix("Beam: ")
+                    .suffix(" enA")
+                    .clamp_range(0.0..=f64::MAX),
+            );
+            ui.add(
+                egui::DragValue::new(&mut settings.target_thickness)
+                    .prefix("Target: ")
+                    .suffix(" μg/cm²")
+                    .clamp_range(0.0..=f64::MAX),
+            );
+            ui.add(
+                egui::DragValue::new(&mut settings.run_time)
+                    .prefix("Time: ")
+                    .suffix(" h")
+                    .speed(0.1)
+                    .clamp_range(0.0..=f64::MAX),
+            );
+            ui.add(
+                egui::DragValue::new(&mut settings.solid_angle)
+                    .prefix("ΔΩ: ")
+                    .suffix(" msr")
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::MAX),
+            );
+            ui.add(
+                egui::DragValue::new(&mut settings.cross_section)
+                    .prefix("dσ/dΩ: ")
+                    .suffix(" mb/sr")
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::MAX),
+            )
+            .on_hover_text("Flat cross section, scaled per level in the excitation levels panel");
+
+            if let Some(max) = self.max_counts() {
+                ui.label(format!("Largest peak ≈ {:.0} counts", max));
+            }
+        });
+    }
+
+    fn field_angle_search_ui(&mut self, ui: &mut egui::Ui) {
+        let total = self
+            .reactions
+            .iter()
+            .map(|reaction| reaction.must_see.len())
+            .sum::<usize>();
+
+        ui.horizontal(|ui| {
+            ui.label(format!("Must see: {} levels", total)).on_hover_text(
+                "Star levels in the excitation levels panel to add them",
+            );
+
+            if ui
+                .add_enabled(total > 0, egui::Button::new("Find Field & Angle"))
+                .on_hover_text(
+                    "Search every field and angle for the most must-see levels inside the acceptance",
+                )
+                .clicked()
+            {
+                self.best_setting = self.best_field_and_angle();
+            }
+
+            if let Some(best) = self.best_setting {
+                ui.label(format!(
+                    "{:.4} {} at {:.1}°: {}/{} inside",
+                    self.field_unit.convert(best.field),
+                    self.field_unit.label(),
+                    best.angle,
+                    best.inside,
+                    best.total
+                ));
+                if ui.button("Apply").clicked() {
+                    self.magnetic_field = best.field;
+                    self.sps_angle = best.angle;
+                    self.calculate_rho_for_all_reactions();
+                }
+            }
+        });
+    }
+
+    // Grid search over field and angle for the most must-see levels inside the acceptance. Ties go to
+    // the setting that keeps them closest to the middle of the focal plane.
+    fn best_field_and_angle(&self) -> Option<BestSetting> {
+        const FIELD_STEP: f64 = 0.05; // kG
+        const ANGLE_STEP: f64 = 1.0; // degrees
+
+        let levels = self
+            .reactions
+            .iter()
+            .flat_map(|reaction| {
+                reaction
+                    .must_see
+                    .iter()
+                    .map(move |level| (reaction, *level))
+            })
+            .collect::<Vec<_>>();
+        if levels.is_empty() {
+            return None;
+        }
+
+        let center = (self.rho_min + self.rho_max) / 2.0;
+        let mut best: Option<(BestSetting, f64)> = None;
+        for angle_step in 0..=(MAX_SPS_ANGLE / ANGLE_STEP) as usize {
+            let angle = angle_step as f64 * ANGLE_STEP;
+            for field_step in 1..=(MAX_FIELD / FIELD_STEP) as usize {
+                let field = field_step as f64 * FIELD_STEP;
+
+                let mut inside = 0;
+                let mut spread = 0.0;
+                for (reaction, excitation) in &levels {
+                    let rho = reaction.rho_at(*excitation, self.beam_energy, field, angle);
+                    if let Some(rho) = rho.filter(|rho| (self.rho_min..=self.rho_max).contains(rho))
+                    {
+                        inside += 1;
+                        spread += (rho - center).powi(2);
+                    }
+                }
+
+                let better = match &best {
+                    None => true,
+                    Some((best, best_spread)) => {
+                        inside > best.inside || (inside == best.inside && spread < *best_spread)
+                    }
+                };
+                if better {
+                    let setting = BestSetting {
+                        field,
+                        angle,
+                        inside,
+                        total: levels.len(),
+                    };
+                    best = Some((setting, spread));
+                }
+            }
+        }
+
+        best.map(|(setting, _)| setting)
+    }
+
+    // Counts of the largest bar drawn, levels closer than the resolution summed like their merged
+    // bar. None when the estimate is off or gives nothing.
+    fn max_counts(&self) -> Option<f64> {
+        if !self.yields.enabled {
+            return None;
+        }
+
+        let (rho_min, rho_max) = (self.rho_min, self.rho_max);
+        let hide_outside = self.outside_acceptance == OutsideAcceptance::Hide;
+        let max = self
+            .reactions
+            .iter()
+            .filter(|reaction| reaction.visible)
+            .chain(&self.comparison)
+            .flat_map(|reaction| {
+                Reaction::unresolved_groups(
+                    reaction.drawn_values(self.ground_state_only),
+                    self.rho_resolution,
+                )
+                .into_iter()
+                .filter(|group| {
+                    !hide_outside
+                        || group
+                            .iter()
+                            .any(|value| (rho_min..=rho_max).contains(&value.rho))
+                })
+                .map(|group| reaction.group_counts(&group, &self.yields))
+                .collect::<Vec<_>>()
+            })
+            .fold(0.0, f64::max);
+        (max > 0.0).then_some(max)
+    }
+
+    fn search_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_search, "Identify Peak")
+                .on_hover_text("Find the levels of every reaction near an observed rho or energy");
+
+            if !self.show_search {
+                return;
+            }
+
+            ui.radio_value(&mut self.search_by_energy, false, "Rho");
+            ui.radio_value(&mut self.search_by_energy, true, "Energy")
+                .on_hover_text(
+                    "An excitation energy of one reaction, matched at the rho it lands on",
+                );
+
+            if self.search_by_energy {
+                ui.add(
+                    egui::DragValue::new(&mut self.search_energy)
+                        .suffix(" MeV")
+                        .speed(0.01),
+                );
+                let selected = self
+                    .reactions
+                    .get(self.search_reaction)
+                    .map_or("None".to_string(), |reaction| {
+                        reaction.reaction_identifier.clone()
+                    });
+                egui::ComboBox::from_id_source("sps_plot_search_reaction")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (index, reaction) in self.reactions.iter().enumerate() {
+                            ui.selectable_value(
+                                &mut self.search_reaction,
+                                index,
+                                format!("{}: {}", index, reaction.reaction_identifier),
+                            );
+                        }
+                    });
+                match self.search_rho() {
+                    Some(rho) => ui.label(format!(
+                        "= {:.4} {}",
+                        self.rho_unit.convert(rho),
+                        self.rho_unit.label()
+                    )),
+                    None => ui.colored_label(Color32::from_rgb(255, 165, 0), "no rho"),
+                };
+            } else {
+                ui.add(
+                    rho_drag_value(&mut self.search_rho, self.rho_unit)
+                        .prefix("Rho: ")
+                        .speed(self.rho_unit.convert(0.01)),
+                );
+            }
+            ui.add(
+                rho_drag_value(&mut self.search_tolerance, self.rho_unit)
+                    .prefix("± ")
+                    .speed(self.rho_unit.convert(0.01)),
+            );
+
+            ui.separator();
+
+            let matches = self.search_matches();
+            if matches.is_empty() {
+                ui.label("No levels in window");
+                return;
+            }
+
+            for (reaction, value, distance) in matches {
+                let reaction = &self.reactions[reaction];
+                let value = &reaction.rho_values[value];
+                ui.colored_label(
+                    reaction.color,
+                    format!(
+                        "{} {:.*} MeV {} ({:+.4} {})",
+                        reaction.reaction_identifier,
+                        self.energy_decimals,
+                        value.excitation,
+                        value.jpi,
+                        self.rho_unit.convert(distance),
+                        self.rho_unit.label()
+                    ),
+                );
+            }
+        });
+    }
+
+    // observed rho (cm), an energy is taken through its reaction's kinematics
+    fn search_rho(&self) -> Option<f64> {
+        if !self.search_by_energy {
+            return Some(self.search_rho);
+        }
+        self.reactions.get(self.search_reaction)?.rho_at(
+            self.search_energy,
+            self.beam_energy,
+            self.magnetic_field,
+            self.sps_angle,
+        )
+    }
+
+    fn search_window(&self) -> Option<(f64, f64)> {
+        let rho = self.search_rho().filter(|_| self.show_search)?;
+        Some((rho - self.search_tolerance, rho + self.search_tolerance))
+    }
+
+    // (reaction, rho value, predicted - observed rho) of every level in the search window, closest first
+    fn search_matches(&self) -> Vec<(usize, usize, f64)> {
+        let Some((min, max)) = self.search_window() else {
+            return Vec::new();
+        };
+        let observed = (min + max) / 2.0;
+
+        let mut matches: Vec<(usize, usize, f64)> = self
+            .reactions
+            .iter()
+            .enumerate()
+            .flat_map(|(reaction_index, reaction)| {
+                reaction
+                    .rho_values
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| (min..=max).contains(&value.rho))
+                    .map(move |(value_index, value)| {
+                        (reaction_index, value_index, value.rho - observed)
+                    })
+            })
+            .collect();
+        matches.sort_by(|a, b| a.2.abs().total_cmp(&b.2.abs()));
+        matches
+    }
+
+    // shared by the scan views, the acceptance band and limits are drawn along y
+    fn draw_scan_acceptance(&self, plot_ui: &mut egui_plot::PlotUi, x_range: (f64, f64)) {
+        plot_ui.polygon(
+            Polygon::new(PlotPoints::new(vec![
+                [x_range.0, self.rho_min],
+                [x_range.1, self.rho_min],
+                [x_range.1, self.rho_max],
+                [x_range.0, self.rho_max],
+            ]))
+            .fill_color(self.acceptance_color)
+            .stroke(Stroke::NONE),
+        );
+        plot_ui.hline(HLine::new(self.rho_min).color(Color32::RED));
+        plot_ui.hline(HLine::new(self.rho_max).color(Color32::RED));
+    }
+
+    fn beam_energy_scan_plot(&mut self, ui: &mut egui::Ui) {
+        const STEPS: usize = 200;
+
+        let plot = Plot::new("SPS Beam Energy Scan")
+            .x_axis_label("Beam Energy (MeV)")
+            .y_axis_label(format!("Rho ({})", self.rho_unit.label()))
+            .y_axis_formatter(rho_tick_formatter(self.rho_unit))
+            .legend(Legend::default());
+
+        let response = plot.show(ui, |plot_ui| {
+            let x_range = (self.scan_beam_energy_min, self.scan_beam_energy_max);
+            self.draw_scan_acceptance(plot_ui, x_range);
+
+            if let Some(reaction) = self.reactions.get(self.scan_reaction) {
+                let step = (x_range.1 - x_range.0) / STEPS as f64;
+                let points = (0..=STEPS)
+                    .filter_map(|index| {
+                        let beam_energy = x_range.0 + index as f64 * step;
+                        let rho = reaction.rho_at(
+                            self.scan_excitation,
+                            beam_energy,
+                            self.magnetic_field,
+                            self.sps_angle,
+                        )?;
+                        rho.is_finite().then_some([beam_energy, rho])
+                    })
+                    .collect::<Vec<_>>();
+
+                plot_ui.line(
+                    Line::new(PlotPoints::new(points))
+                        .color(reaction.color)
+                        .name(format!(
+                            "{} {:.*} MeV",
+                            reaction.reaction_identifier,
+                            self.energy_decimals,
+                            self.scan_excitation
+                        )),
+                );
+            }
+
+            plot_ui.vline(
+                VLine::new(self.beam_energy)
+                    .color(Color32::GRAY)
+                    .name("Beam Energy"),
+            );
+        });
+
+        self.plot_rect = Some(response.response.rect);
+    }
+
+    fn angle_scan_plot(&mut self, ui: &mut egui::Ui) {
+        const ANGLE_STEP: f64 = 0.5; // degree
+
+        let plot = Plot::new("SPS Angle Scan")
+            .x_axis_label("SPS Angle (°)")
+            .y_axis_label(format!("Rho ({})", self.rho_unit.label()))
+            .y_axis_formatter(rho_tick_formatter(self.rho_unit))
+            .legend(Legend::default());
+
+        let response = plot.show(ui, |plot_ui| {
+            let x_range = (0.0, MAX_SPS_ANGLE);
+            self.draw_scan_acceptance(plot_ui, x_range);
+
+            if let Some(reaction) = self.reactions.get(self.scan_reaction) {
+                let points = (0..=(x_range.1 / ANGLE_STEP) as usize)
+                    .filter_map(|step| {
+                        let angle = step as f64 * ANGLE_STEP;
+                        let rho = reaction.rho_at(
+                            self.scan_excitation,
+                            self.beam_energy,
+                            self.magnetic_field,
+                            angle,
+                        )?;
+                        rho.is_finite().then_some([angle, rho])
+                    })
+                    .collect::<Vec<_>>();
+
+                plot_ui.line(
+                    Line::new(PlotPoints::new(points))
+                        .color(reaction.color)
+                        .name(format!(
+                            "{} {:.*} MeV",
+                            reaction.reaction_identifier,
+                            self.energy_decimals,
+                            self.scan_excitation
+                        )),
+                );
+            }
+
+            plot_ui.vline(
+                VLine::new(self.sps_angle)
+                    .color(Color32::GRAY)
+                    .name("SPS Angle"),
+            );
+        });
+
+        self.plot_rect = Some(response.response.rect);
+    }
+
+    fn spectrum_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Spectrum: ");
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Load").clicked() {
+                self.load_spectrum();
+            }
+
+            if self.spectrum.is_empty() {
+                return;
+            }
+
+            ui.checkbox(&mut self.show_spectrum, "Show");
+            ui.color_edit_button_srgba(&mut self.spectrum_color);
+
+            if ui.button("Clear").clicked() {
+                self.spectrum.clear();
+            }
+
+            ui.checkbox(&mut self.spectrum_log, "Log")
+                .on_hover_text("Log scale counts so weak peaks next to strong ones are visible");
+
+            ui.checkbox(&mut self.subtract_background, "Subtract Background")
+                .on_hover_text("Remove a rolling minimum over the given number of channels");
+            if self.subtract_background {
+                ui.add(
+                    egui::DragValue::new(&mut self.background_window)
+                        .suffix(" ch")
+                        .clamp_range(1..=4096),
+                );
+            }
+
+            ui.separator();
+
+            for (index, point) in self.calibration_points.iter_mut().enumerate() {
+                ui.label(format!("Point {}: ", index + 1));
+                ui.add(egui::DragValue::new(&mut point[0]).prefix("Channel: "));
+                ui.add(
+                    rho_drag_value(&mut point[1], self.rho_unit)
+                        .prefix("Rho: ")
+                        .speed(self.rho_unit.convert(0.01)),
+                );
+            }
+
+            if ui.button("Calibrate").clicked() {
+                self.calibrate();
+            }
+
+            // the coefficients are fitted in cm, m needs two more decimals for the same digits
+            let unit = self.rho_unit;
+            let extra = if unit == RhoUnit::Meter { 2 } else { 0 };
+            if self.quadratic == 0.0 {
+                ui.label(format!(
+                    "Rho ({}) = {:.*} * Channel + {:.*}",
+                    unit.label(),
+                    6 + extra,
+                    unit.convert(self.slope),
+                    3 + extra,
+                    unit.convert(self.offset)
+                ));
+            } else {
+                ui.label(format!(
+                    "Rho ({}) = {:.3e} * Channel² + {:.*} * Channel + {:.*}",
+                    unit.label(),
+                    unit.convert(self.quadratic),
+                    6 + extra,
+                    unit.convert(self.slope),
+                    3 + extra,
+                    unit.convert(self.offset)
+                ));
+            }
+        });
+
+        if !self.spectrum.is_empty() {
+            self.energy_calibration_ui(ui);
+        }
+    }
+
+    // calibrated spectrum scaled so its tallest bin spans the plot height
+    fn draw_spectrum(&self, plot_ui: &mut egui_plot::PlotUi, y_top: f64) {
+        let mut bins = if self.subtract_background {
+            subtract_background(&self.spectrum, self.background_window)
+        } else {
+            self.spectrum.clone()
+        };
+
+        // log10(counts + 1) keeps empty bins at zero
+        if self.spectrum_log {
+            for [_, counts] in &mut bins {
+                *counts = counts.max(0.0).ln_1p() / std::f64::consts::LN_10;
+            }
+        }
+
+        let max_counts = bins.iter().map(|[_, counts]| *counts).fold(0.0, f64::max);
+        if max_counts <= 0.0 {
+            return;
+        }
+
+        let scale = (y_top + 1.0) / max_counts;
+        let points = bins
+            .iter()
+            .map(|[channel, counts]| {
+                let rho = self.channel_to_rho(*channel);
+                self.plot_orientation.point(rho, counts * scale - 1.0)
+            })
+            .collect::<Vec<_>>();
+
+        plot_ui.line(
+            Line::new(PlotPoints::new(points))
+                .color(self.spectrum_color)
+                .name("Spectrum"),
+        );
+    }
+
+    // version and where the masses and levels behind the plot came from, for publications
+    fn about_ui(&self, ui: &mut egui::Ui) {
+        ui.strong(format!("SPS Plot {}", env!("CARGO_PKG_VERSION")));
+
+        ui.separator();
+
+        ui.label(format!(
+            "Masses: {}",
+            match self.mass_evaluation.in_use() {
+                MassEvaluation::Ame2016 => MassSource::Ame2016.label(),
+                MassEvaluation::Ame2020 => MassSource::Ame2020.label(),
+            }
+        ));
+        if let Some(path) = &self.ame2020_path {
+            ui.label(format!("AME2020 file: {}", path.display()));
+        }
+        if let Some(path) = &self.levels_csv_path {
+            ui.label(format!("Levels file: {}", path.display()));
+        }
+        ui.label(format!("Levels: {}", self.data_source.provenance()));
+
+        if self.reactions.is_empty() {
+            return;
+        }
+
+        ui.separator();
+
+        egui::Grid::new("sps_plot_about_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Reaction");
+                ui.strong("Masses");
+                ui.strong("Levels");
+                ui.end_row();
+
+                for reaction in &self.reactions {
+                    let mut sources: Vec<&str> = [
+                        &reaction.target_data,
+                        &reaction.projectile_data,
+                        &reaction.ejectile_data,
+                        &reaction.resid_data,
+                    ]
+                    .iter()
+                    .filter_map(|data| data.as_ref().map(|data| data.source.label()))
+                    .collect();
+                    sources.sort_unstable();
+                    sources.dedup();
+
+                    ui.label(&reaction.reaction_identifier);
+                    ui.label(sources.join(", "));
+                    ui.label(&reaction.levels_source);
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn sps_settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::global_dark_light_mode_switch(ui);
+            self.dark_mode = ui.visuals().dark_mode;
+
+            ui.heading("SE-SPS Settings");
+
+            ui.menu_button("About", |ui| self.about_ui(ui));
+
+            // NNDC fetches run in the background, show they aren't stuck
+            if !self.pending_fetches.is_empty() {
+                let fetched = self.fetch_total - self.pending_fetches.len();
+                ui.separator();
+                ui.spinner();
+                ui.add(
+                    egui::ProgressBar::new(fetched as f32 / self.fetch_total as f32)
+                        .desired_width(150.0)
+                        .text(format!("Fetched {}/{} isotopes", fetched, self.fetch_total)),
+                );
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.separator();
+
+                if ui.button("Save Project").clicked() {
+                    self.save_project();
+                }
+
+                if ui.button("Load Project").clicked() {
+                    self.load_project();
+                }
+
+                if self.comparison_label.is_empty() {
+                    if ui
+                        .button("Compare")
+                        .on_hover_text(
+                            "Overlay the levels of another project, muted, to compare run plans",
+                        )
+                        .clicked()
+                    {
+                        self.load_comparison();
+                    }
+                } else {
+                    ui.label(format!("Comparing {}", self.comparison_label));
+                    if ui.button("✖").on_hover_text("Stop comparing").clicked() {
+                        self.comparison.clear();
+                        self.comparison_label.clear();
+                    }
+                }
+
+                ui.separator();
+
+                if ui
+                    .button("Export PNG")
+                    .on_hover_text("Save the plot as it appears on screen")
+                    .clicked()
+                {
+                    self.png_export_requested = true;
+                    ui.ctx()
+                        .send_viewport_cmd(egui::ViewportCommand::Screenshot);
+                }
+                ui.add(
+                    egui::DragValue::new(&mut self.png_resize)
+                        .prefix("Resize: ")
+                        .suffix("x")
+                        .speed(0.1)
+                        .clamp_range(0.5..=8.0),
+                )
+                .on_hover_text(
+                    "Resamples the screenshot, it adds pixels but no detail. Export SVG for a sharp figure",
+                );
+
+                ui.separator();
+
+                if ui
+                    .button("Export SVG")
+                    .on_hover_text("Save the focal plane plot as a vector image for publication")
+                    .clicked()
+                {
+                    self.export_svg();
+                }
+
+                if ui
+                    .button("Export CSV")
+                    .on_hover_text("Save the calculated rho values of every reaction")
+                    .clicked()
+                {
+                    self.export_csv();
+                }
+
+                if ui
+                    .button("Export LaTeX")
+                    .on_hover_text("Save the reactions and their key numbers as a LaTeX tabular")
+                    .clicked()
+                {
+                    self.export_latex();
+                }
+                ui.menu_button("⏷", |ui| {
+                    let columns = &mut self.latex_columns;
+                    ui.checkbox(&mut columns.q_value, "Q-value");
+                    ui.checkbox(&mut columns.ground_state_rho, "Ground state ρ");
+                    ui.checkbox(&mut columns.max_excitation, "Max Ex on the focal plane")
+                        .on_hover_text("Excitation energy landing at the low rho edge");
+                })
+                .response
+                .on_hover_text("Columns of the LaTeX table");
+
+                if let Some(message) = &self.file_message {
+                    ui.colored_label(Color32::RED, message);
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("SPS Angle: ").on_hover_text(format!(
+                "SE-SPS's angle currently limited to {}°",
+                MAX_SPS_ANGLE
+            ));
+            ui.add(
+                egui::DragValue::new(&mut self.sps_angle)
+                    .suffix("°")
+                    .clamp_range(0.0..=MAX_SPS_ANGLE),
+            );
+            // the kinematics are continuous through 0°, but the unreacted beam lands on the focal plane too
+            if self.sps_angle == 0.0 {
+                ui.colored_label(Color32::from_rgb(255, 165, 0), "⚠")
+                    .on_hover_text("At 0° the beam also enters the spectrograph");
+            }
+
+            ui.label("Beam Energy: ");
+            ui.add_enabled(
+                !self.tandem.enabled,
+                egui::DragValue::new(&mut self.beam_energy)
+                    .suffix(" MeV")
+                    .clamp_range(0.0..=f64::MAX),
+            );
+            self.tandem_ui(ui);
+
+            ui.label("Magnetic Field: ");
+            ui.add(field_drag_value(
+                &mut self.magnetic_field,
+                self.field_unit,
+                1.0,
+            ));
+
+            // extra settings are shown faded to plan which field covers which states
+            if ui
+                .button("+")
+                .on_hover_text("Compare another field setting")
+                .clicked()
+            {
+                self.field_settings.push(self.magnetic_field);
+            }
+            let mut to_remove: Option<usize> = None;
+            for (index, field) in self.field_settings.iter_mut().enumerate() {
+                ui.add(field_drag_value(field, self.field_unit, 0.01));
+                if ui.small_button("x").clicked() {
+                    to_remove = Some(index);
+                }
+            }
+            if let Some(index) = to_remove {
+                self.field_settings.remove(index);
+            }
+
+            ui.label("Step: ").on_hover_text(
+                "Arrow keys change the beam energy (left/right) and field (up/down) by these amounts while hovering the plot",
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.beam_energy_step)
+                    .suffix(" MeV")
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::MAX),
+            );
+            ui.add(field_drag_value(
+                &mut self.field_step,
+                self.field_unit,
+                0.001,
+            ));
+
+            ui.label("Rho Min: ")
+                .on_hover_text("SE-SPS Rho Min is usually 69.0 cm");
+            ui.add(rho_drag_value(&mut self.rho_min, self.rho_unit));
+
+            ui.label("Rho Max: ")
+                .on_hover_text("SE-SPS Rho Max is usually 87.0 cm");
+            ui.add(rho_drag_value(&mut self.rho_max, self.rho_unit));
+
+            let (p_min, p_max) = (
+                QBRHO2P * self.magnetic_field * self.rho_min,
+                QBRHO2P * self.magnetic_field * self.rho_max,
+            );
+            ui.label(format!(
+                "Δp/p: {:.1} %",
+                100.0 * momentum_acceptance(self.rho_min, self.rho_max)
+            ))
+            .on_hover_text(format!(
+                "Momentum acceptance of the focal plane, {:.2} to {:.2} MeV/c per unit charge at this field",
+                p_min, p_max
+            ));
+
+            ui.label("Resolution: ").on_hover_text(
+                "Focal plane rho resolution, levels closer than this are drawn as one unresolved group (0 to turn off)",
+            );
+            ui.add(
+                rho_drag_value(&mut self.rho_resolution, self.rho_unit)
+                    .speed(self.rho_unit.convert(0.01)),
+            );
+
+            ui.color_edit_button_srgba(&mut self.acceptance_color)
+                .on_hover_text("Shading of the focal plane acceptance");
+
+            ui.separator();
+
+            if ui.button("Calculate").clicked() {
+                self.calculate_rho_for_all_reactions();
+            }
+            ui.checkbox(&mut self.auto_calc, "Auto")
+                .on_hover_text("Calculate again whenever the beam, field, angle or a reaction changes");
+
+            ui.separator();
+
+            ui.checkbox(&mut self.side_panel, "Show Exciation Levels");
+            ui.checkbox(&mut self.results_panel, "Results Table")
+                .on_hover_text("Every calculated level in a sortable table");
+
+            ui.label("Rho Axis: ");
+            ui.radio_value(
+                &mut self.plot_orientation,
+                PlotOrientation::RhoAlongX,
+                "Horizontal (x)",
+            );
+            ui.radio_value(
+                &mut self.plot_orientation,
+                PlotOrientation::RhoAlongY,
+                "Vertical (y)",
+            );
+
+            ui.label("Units: ")
+                .on_hover_text("SE-SPS settings are usually given in kG and cm");
+            ui.selectable_value(&mut self.field_unit, FieldUnit::Kilogauss, "kG");
+            ui.selectable_value(&mut self.field_unit, FieldUnit::Tesla, "T");
+            ui.selectable_value(&mut self.rho_unit, RhoUnit::Centimeter, "cm");
+            ui.selectable_value(&mut self.rho_unit, RhoUnit::Meter, "m");
+
+            ui.checkbox(&mut self.show_momentum, "p, Bρ").on_hover_text(
+                "Include the ejectile momentum and rigidity in the exported CSV",
+            );
+
+            ui.label("Outside: ")
+                .on_hover_text("How levels outside the focal plane acceptance are drawn");
+            egui::ComboBox::from_id_source("sps_plot_outside_acceptance")
+                .selected_text(self.outside_acceptance.label())
+                .show_ui(ui, |ui| {
+                    for mode in OutsideAcceptance::ALL {
+                        ui.selectable_value(&mut self.outside_acceptance, mode, mode.label());
+                    }
+                });
+
+            self.palette_ui(ui);
+
+            ui.checkbox(&mut self.color_by_spin, "Color by J")
+                .on_hover_text("Color levels by spin, levels without a J keep the reaction color");
+
+            ui.checkbox(&mut self.lock_bounds, "Lock View")
+                .on_hover_text("Unlock to zoom (scroll or box) and pan the plot");
+            if !self.lock_bounds
+                && ui
+                    .button("Auto-fit")
+                    .on_hover_text("Zoom to the calculated levels")
+                    .clicked()
+            {
+                self.auto_fit_requested = true;
+            }
+
+            ui.checkbox(&mut self.show_overview, "Overview").on_hover_text(
+                "Strip beside the plot with every reaction row, click or drag it to move the view",
+            );
+
+            ui.checkbox(&mut self.ground_state_only, "Ground States")
+                .on_hover_text("Only plot the lowest level of each reaction, for tuning the beam");
+
+            ui.checkbox(&mut self.show_peak_labels, "Peak Labels")
+                .on_hover_text("Label each peak with its excitation energy in MeV");
+
+            ui.checkbox(&mut self.show_crosshair, "Crosshair").on_hover_text(
+                "Read off the rho under the cursor and its excitation energy, for the Energy Axis reaction or else the row under the cursor",
+            );
+
+            ui.add(
+                egui::DragValue::new(&mut self.energy_decimals)
+                    .prefix("Decimals: ")
+                    .clamp_range(0..=6),
+            )
+            .on_hover_text("Decimals shown for excitation energies (MeV), 3 is keV. NNDC levels are stored at full precision");
+
+            ui.add(
+                egui::DragValue::new(&mut self.bar_height)
+                    .prefix("Bar Height: ")
+                    .speed(0.01)
+                    .clamp_range(0.05..=self.reaction_spacing),
+            );
+            ui.add(
+                rho_drag_value(&mut self.bar_width, self.rho_unit)
+                    .prefix("Width: ")
+                    .speed(self.rho_unit.convert(0.001))
+                    .clamp_range(self.rho_unit.convert(0.001)..=self.rho_unit.convert(1.0)),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.reaction_spacing)
+                    .prefix("Spacing: ")
+                    .speed(0.01)
+                    .clamp_range(0.1..=5.0),
+            )
+            .on_hover_text("Distance between stacked reactions, lower it to fit many reactions");
+
+            let selected = match self.energy_axis_reaction {
+                Some(index) if index < self.reactions.len() => {
+                    self.reactions[index].reaction_identifier.clone()
+                }
+                _ => "None".to_string(),
+            };
+            egui::ComboBox::from_label("Energy Axis")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.energy_axis_reaction, None, "None");
+                    for (index, reaction) in self.reactions.iter().enumerate() {
+                        ui.selectable_value(
+                            &mut self.energy_axis_reaction,
+                            Some(index),
+                            format!("{}: {}", index, reaction.reaction_identifier),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Show the excitation energy of a reaction along the top of the plot",
+                );
+
+            ui.separator();
+
+            ui.label("Masses: ");
+            ui.radio_value(
+                &mut self.mass_evaluation,
+                MassEvaluation::Ame2016,
+                "AME2016",
+            );
+            // AME2020 isn't bundled and its file can't be loaded on the web
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.radio_value(
+                &mut self.mass_evaluation,
+                MassEvaluation::Ame2020,
+                "AME2020",
+            )
+            .on_hover_text(
+                "Read from the AMDC mass_1.mas20 file, AME2016 is used until one is loaded",
+            );
+            #[cfg(not(target_arch = "wasm32"))]
+            if self.mass_evaluation == MassEvaluation::Ame2020 && ui.button("Load").clicked() {
+                self.load_ame2020();
+            }
+            if self.mass_evaluation.in_use() != self.mass_evaluation {
+                ui.colored_label(Color32::from_rgb(255, 165, 0), "using AME2016");
+            }
+
+            ui.separator();
+
+            ui.label("Levels: ");
+            ui.radio_value(&mut self.data_source, DataSource::Bundled, "Bundled")
+                .on_hover_text(format!(
+                    "Levels scraped from NNDC on {}, works offline",
+                    BUNDLED_LEVELS_RETRIEVED
+                ));
+            // the NNDC scraper isn't built for the web, only the bundled levels are offered there
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if self.data_source == DataSource::Bundled {
+                    match &self.levels_csv_path {
+                        Some(path) => {
+                            ui.label(format!(
+                                "from {}",
+                                path.file_name().unwrap_or_default().to_string_lossy()
+                            ))
+                            .on_hover_text(path.display().to_string());
+                            if ui
+                                .button("✖")
+                                .on_hover_text("Go back to the bundled levels")
+                                .clicked()
+                            {
+                                super::excitation_levels_nndc::set_imported_levels(None);
+                                self.levels_csv_path = None;
+                            }
+                        }
+                        None => {
+                            if ui
+                                .button("Import")
+                                .on_hover_text("Use an excitation_levels.csv rebuilt with nndc_excitation_level_getter in place of the bundled levels")
+                                .clicked()
+                            {
+                                self.load_levels_csv();
+                            }
+                        }
+                    }
+                }
+
+                ui.radio_value(&mut self.data_source, DataSource::Nndc, "NNDC")
+                    .on_hover_text("Fetch the current levels from NNDC");
+            }
+        });
+
+        self.spectrum_ui(ui);
+        self.scan_ui(ui);
+        self.search_ui(ui);
+        self.yield_ui(ui);
+        self.field_angle_search_ui(ui);
+    }
+
+    // cycles through the palette as reactions are added
+    fn next_reaction_color(&self) -> Color32 {
+        let colors = self.palette_colors();
+        colors[self.reactions.len() % colors.len()]
+    }
+
+    fn palette_colors(&self) -> &[Color32] {
+        match self.palette {
+            ColorPalette::Custom if !self.custom_palette.is_empty() => &self.custom_palette,
+            palette => palette.colors(),
+        }
+    }
+
+    fn palette_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Palette: ")
+            .on_hover_text("Colors given to new reactions");
+        egui::ComboBox::from_id_source("sps_plot_color_palette")
+            .selected_text(self.palette.label())
+            .show_ui(ui, |ui| {
+                for palette in ColorPalette::ALL {
+                    ui.selectable_value(&mut self.palette, palette, palette.label());
+                }
+            });
+
+        if self.palette == ColorPalette::Custom {
+            let mut to_remove: Option<usize> = None;
+            for (index, color) in self.custom_palette.iter_mut().enumerate() {
+                ui.color_edit_button_srgba(color)
+                    .on_hover_text("Right click to remove")
+                    .context_menu(|ui| {
+                        if ui.button("Remove").clicked() {
+                            to_remove = Some(index);
+                            ui.close_menu();
+                        }
+                    });
+            }
+            if let Some(index) = to_remove {
+                self.custom_palette.remove(index);
+            }
+            if ui.button("+").clicked() {
+                self.custom_palette.push(Color32::GRAY);
+            }
+        }
+
+        if ui
+            .button("Recolor")
+            .on_hover_text("Give every reaction its palette color, contaminants stay gray")
+            .clicked()
+        {
+            let colors = self.palette_colors().to_vec();
+            for (index, reaction) in self.reactions.iter_mut().enumerate() {
+                if !reaction.contaminant {
+                    reaction.color = colors[index % colors.len()];
+                }
+            }
+        }
+    }
+
+    // cycles with the colors so neighbouring reactions differ in both
+    fn next_reaction_style(&self) -> BarStyle {
+        BarStyle::ALL[self.reactions.len() % BarStyle::ALL.len()]
+    }
+
+    // same beam and ejectile on the common contaminants, skipping ones that can't happen
+    fn add_contaminant_reactions(&mut self, index: usize) {
+        let primary = &self.reactions[index];
+        let (projectile, ejectile) = (
+            (primary.projectile_z, primary.projectile_a),
+            (primary.ejectile_z, primary.ejectile_a),
+        );
+        let target = (primary.target_z, primary.target_a);
+
+        for contaminant in CONTAMINANT_TARGETS {
+            if contaminant == target {
+                continue;
+            }
+
+            let mut reaction = Reaction {
+                target_z: contaminant.0,
+                target_a: contaminant.1,
+                projectile_z: projectile.0,
+                projectile_a: projectile.1,
+                ejectile_z: ejectile.0,
+                ejectile_a: ejectile.1,
+                contaminant: true,
+                style: self.next_reaction_style(),
+                ..Reaction::new(Color32::GRAY)
+            };
+            Reaction::populate_reaction_data(&mut reaction, self.mass_evaluation);
+            if reaction.nuclei().is_none() {
+                log::info!(
+                    "Skipping contaminant {}, no residual nucleus",
+                    reaction.reaction_identifier
+                );
+                continue;
+            }
+
+            Reaction::fetch_excitation_levels(&mut reaction, self.data_source, false);
+            reaction.calculate_rho(
+                self.mass_evaluation,
+                self.beam_energy,
+                self.magnetic_field,
+                self.sps_angle,
+            );
+            self.reactions.push(reaction);
+        }
+    }
+
+    fn reactions_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Reactions");
+
+            ui.separator();
+
+            if ui.button("Calculate").clicked() {
+                self.calculate_rho_for_all_reactions();
+            }
+
+            ui.separator();
+
+            if ui
+                .add_enabled(
+                    self.pending_fetches.is_empty(),
+                    egui::Button::new("Fetch All"),
+                )
+                .on_hover_text(
+                    "Get every reaction and look up its excitation levels, hold shift to refresh saved levels",
+                )
+                .clicked()
+            {
+                let refresh = ui.input(|i| i.modifiers.shift);
+                self.fetch_all_excitation_levels(ui.ctx(), refresh);
+            }
+            if !self.pending_fetches.is_empty() {
+                ui.spinner();
+                let pending = self
+                    .pending_fetches
+                    .iter()
+                    .map(|(isotope, dataset)| format!("{} {}", isotope, dataset).trim().to_string())
+                    .collect::<Vec<_>>();
+                ui.label(format!("Fetching {}", pending.join(", ")));
+            }
+
+            let below_threshold = self
+                .reactions
+                .iter()
+                .filter(|reaction| reaction.below_threshold(self.beam_energy).is_some())
+                .count();
+            if below_threshold > 0 {
+                ui.colored_label(
+                    Color32::RED,
+                    format!("⚠ {} below threshold", below_threshold),
+                )
+                .on_hover_text("Endothermic reactions the current beam energy can't drive");
+            }
+
+            ui.separator();
+
+            if ui.button("+").clicked() {
+                let reaction = Reaction {
+                    style: self.next_reaction_style(),
+                    ..Reaction::new(self.next_reaction_color())
+                };
+                self.reactions.push(reaction);
+            }
+
+            egui::ComboBox::from_id_source("sps_plot_reaction_presets")
+                .selected_text("Presets")
+                .show_ui(ui, |ui| {
+                    for preset in REACTION_PRESETS.iter() {
+                        if ui.selectable_label(false, preset.0).clicked() {
+                            let mut reaction =
+                                Reaction::from_preset(preset, self.next_reaction_color());
+                            reaction.style = self.next_reaction_style();
+                            Reaction::populate_reaction_data(&mut reaction, self.mass_evaluation);
+                            Reaction::fetch_excitation_levels(
+                                &mut reaction,
+                                self.data_source,
+                                false,
+                            );
+                            self.reactions.push(reaction);
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Add a common SE-SPS reaction");
+
+            ui.separator();
+
+            if ui
+                .button("Clear Levels")
+                .on_hover_text("Remove the levels of every reaction, keeping the reactions")
+                .clicked()
+            {
+                for reaction in &mut self.reactions {
+                    reaction.excitation_levels.clear();
+                    reaction.levels_source.clear();
+                    reaction.levels_fetched_at.clear();
+                    reaction.additional_excitation_levels.clear();
+                    reaction.rho_values.clear();
+                }
+            }
+
+            // removing every reaction takes a second click, Undo Remove brings them back
+            if self.confirm_clear_reactions {
+                ui.colored_label(Color32::from_rgb(255, 165, 0), "Remove all reactions?");
+                if ui.button("Yes").clicked() {
+                    self.clear_reactions();
+                    self.confirm_clear_reactions = false;
+                }
+                if ui.button("No").clicked() {
+                    self.confirm_clear_reactions = false;
+                }
+            } else if ui
+                .add_enabled(!self.reactions.is_empty(), egui::Button::new("Clear All"))
+                .on_hover_text("Remove every reaction")
+                .clicked()
+            {
+                self.confirm_clear_reactions = true;
+            }
+
+            if let Some((_, removed)) = &self.removed_reactions {
+                let restored = match removed.as_slice() {
+                    [reaction] => reaction.reaction_identifier.clone(),
+                    removed => format!("{} reactions", removed.len()),
+                };
+                if ui
+                    .button("Undo Remove")
+                    .on_hover_text(format!("Restore {}", restored))
+                    .clicked()
+                {
+                    self.undo_remove();
+                }
+            }
+        });
+
+        egui::ScrollArea::both().show(ui, |ui| {
+            ui.separator();
+
+            let mut index_to_remove: Option<usize> = None;
+            let mut index_to_duplicate: Option<usize> = None;
+            let mut index_for_contaminants: Option<usize> = None;
+            let mut swap: Option<(usize, usize)> = None;
+            let mut suggested_field: Option<f64> = None;
+            let count = self.reactions.len();
+            // a secondary can only hang off a reaction that isn't one itself
+            let primaries = (0..count)
+                .filter(|index| parent_of(&self.reactions, *index).is_none())
+                .collect::<Vec<_>>();
+            let parents = (0..count)
+                .filter_map(|index| parent_of(&self.reactions, index))
+                .collect::<Vec<_>>();
+            let duplicates = (0..count)
+                .map(|index| duplicate_of(&self.reactions, index))
+                .collect::<Vec<_>>();
+            let mut merge: Option<(usize, usize)> = None; // (into, from)
+            // moving swaps with the previous or next reaction under the same parent, as listed
+            let order = reaction_order(&self.reactions);
+            let mut neighbours = vec![(None, None); count];
+            for (position, (index, _)) in order.iter().enumerate() {
+                let parent = parent_of(&self.reactions, *index);
+                let sibling = |other: &&(usize, bool)| parent_of(&self.reactions, other.0) == parent;
+                neighbours[*index] = (
+                    order[..position].iter().rev().find(sibling).map(|(other, _)| *other),
+                    order[position + 1..].iter().find(sibling).map(|(other, _)| *other),
+                );
+            }
+
+            for (index, secondary) in order {
+                let reaction = &mut self.reactions[index];
+                ui.horizontal(|ui| {
+                    if secondary {
+                        ui.add_space(20.0);
+                        ui.label("↳");
+                    }
+
+                    ui.checkbox(&mut reaction.visible, "")
+                        .on_hover_text("Show on the plot, same as clicking its legend entry");
+
+                    let label = if reaction.contaminant {
+                        ui.label(format!("Reaction {} (contaminant)", index))
+                    } else {
+                        ui.label(format!("Reaction {}", index))
+                    };
+                    if !reaction.notes.is_empty() {
+                        label.on_hover_text(&reaction.notes);
+                    }
+
+                    if let Some((q_value, threshold)) = reaction.below_threshold(self.beam_energy) {
+                        ui.colored_label(Color32::RED, "⚠").on_hover_text(format!(
+                            "Endothermic, Q = {:.3} MeV. Needs a {:.3} MeV beam, {:.3} MeV is below threshold",
+                            q_value, threshold, self.beam_energy
+                        ));
+                    }
+
+                    if let Some(max_angle) = reaction.double_valued(self.beam_energy) {
+                        ui.colored_label(Color32::from_rgb(255, 165, 0), "two-valued")
+                            .on_hover_text(format!(
+                                "The ejectile has a fast and a slow energy at each angle up to {:.1}° for the ground state. The fast one is plotted and levels past their maximum angle are left off.",
+                                max_angle
+                            ));
+                    }
+
+                    if let Some(original) = duplicates[index] {
+                        ui.colored_label(
+                            Color32::from_rgb(255, 165, 0),
+                            format!("duplicate of Reaction {}", original),
+                        )
+                        .on_hover_text("Same target, projectile and ejectile, the bars overlap");
+                        if ui
+                            .button("Merge")
+                            .on_hover_text(format!(
+                                "Move its additional levels, markers and notes to Reaction {} and remove it",
+                                original
+                            ))
+                            .clicked()
+                        {
+                            merge = Some((original, index));
+                        }
+                        if ui.button("Remove").clicked() {
+                            index_to_remove = Some(index);
+                        }
+                    }
+
+                    ui.separator();
+
+                    // fetched levels are lost on removal, so ask first
+                    if self.confirm_remove_reaction == Some(index) {
+                        ui.colored_label(Color32::from_rgb(255, 165, 0), "Remove?");
+                        if ui.button("Yes").clicked() {
+                            index_to_remove = Some(index);
+                            self.confirm_remove_reaction = None;
+                        }
+                        if ui.button("No").clicked() {
+                            self.confirm_remove_reaction = None;
+                        }
+                    } else if ui.button("-").clicked() {
+                        if reaction.excitation_levels.is_empty() {
+                            index_to_remove = Some(index);
+                        } else {
+                            self.confirm_remove_reaction = Some(index);
+                        }
+                    }
+
+                    if ui
+                        .button("Copy")
+                        .on_hover_text("Duplicate this reaction, keeping its levels")
+                        .clicked()
+                    {
+                        index_to_duplicate = Some(index);
+                    }
+
+                    if ui
+                        .button("+C/O/H")
+                        .on_hover_text("Add the same beam and ejectile on 12C, 16O, 1H and 2H")
+                        .clicked()
+                    {
+                        index_for_contaminants = Some(index);
+                    }
+
+                    if ui
+                        .add_enabled(!reaction.rho_values.is_empty(), egui::Button::new("📋"))
+                        .on_hover_text("Copy the energies and rho values to the clipboard")
+                        .clicked()
+                    {
+                        let text = reaction.rho_values_tsv(self.rho_unit);
+                        ui.output_mut(|output| output.copied_text = text);
+                    }
+
+                    // reactions are stacked in list order on the plot
+                    let (previous, next) = neighbours[index];
+                    if ui
+                        .add_enabled(previous.is_some(), egui::Button::new("⬆"))
+                        .on_hover_text("Move up the list")
+                        .clicked()
+                    {
+                        swap = previous.map(|previous| (previous, index));
+                    }
+                    if ui
+                        .add_enabled(next.is_some(), egui::Button::new("⬇"))
+                        .on_hover_text("Move down the list")
+                        .clicked()
+                    {
+                        swap = next.map(|next| (index, next));
+                    }
+
+                    let parent_text = match reaction.parent.filter(|_| secondary) {
+                        Some(parent) => format!("Secondary of {}", parent),
+                        None => "Primary".to_string(),
+                    };
+                    ui.add_enabled_ui(!parents.contains(&index), |ui| {
+                        egui::ComboBox::from_id_source(("sps_plot_reaction_parent", index))
+                            .selected_text(parent_text)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut reaction.parent, None, "Primary");
+                                for parent in primaries.iter().filter(|parent| **parent != index) {
+                                    ui.selectable_value(
+                                        &mut reaction.parent,
+                                        Some(*parent),
+                                        format!("Secondary of {}", parent),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "Group a decay or sequential step under the reaction that feeds it",
+                            );
+                    });
+
+                    reaction.settings_ui(ui, self.data_source, self.mass_evaluation);
+
+                    let off_plane = reaction
+                        .rho_values
+                        .iter()
+                        .filter(|value| !(self.rho_min..=self.rho_max).contains(&value.rho))
+                        .count();
+                    if off_plane > 0 {
+                        ui.colored_label(
+                            Color32::from_rgb(255, 165, 0),
+                            format!("{} levels off-plane", off_plane),
+                        );
+
+                        if let Some(field) = reaction.field_to_center_level(
+                            0.0,
+                            self.beam_energy,
+                            self.sps_angle,
+                            (self.rho_min + self.rho_max) / 2.0,
+                        ) {
+                            if ui
+                                .button(format!(
+                                    "Use {:.4} {}",
+                                    self.field_unit.convert(field),
+                                    self.field_unit.label()
+                                ))
+                                .on_hover_text("Magnetic field that puts the ground state in the middle of the focal plane")
+                                .clicked()
+                            {
+                                suggested_field = Some(field);
+                            }
+                        }
+                    }
+                });
+            }
+
+            if let Some(field) = suggested_field {
+                self.magnetic_field = field;
+                self.calculate_rho_for_all_reactions();
+            }
+
+            if let Some((a, b)) = swap {
+                self.reactions.swap(a, b);
+                for reaction in &mut self.reactions {
+                    reaction.parent = match reaction.parent {
+                        Some(parent) if parent == a => Some(b),
+                        Some(parent) if parent == b => Some(a),
+                        other => other,
+                    };
+                }
+                self.confirm_remove_reaction = None;
+                self.highlighted_level = None;
+
+                // keep the energy axis on the same reaction
+                self.energy_axis_reaction = match self.energy_axis_reaction {
+                    Some(index) if index == a => Some(b),
+                    Some(index) if index == b => Some(a),
+                    other => other,
+                };
+            }
+
+            if let Some(index) = index_to_duplicate {
+                let mut reaction = self.reactions[index].clone();
+                reaction.color = self.next_reaction_color();
+                reaction.style = self.next_reaction_style();
+                reaction.reaction_identifier.push_str(" (copy)");
+                reaction.copy = true;
+                self.reactions.push(reaction);
+            }
+
+            if let Some(index) = index_for_contaminants {
+                self.add_contaminant_reactions(index);
+            }
+
+            if let Some((into, from)) = merge {
+                let duplicate = self.reactions[from].clone();
+                self.reactions[into].merge(duplicate);
+                index_to_remove = Some(from);
+            }
+
+            if let Some(index) = index_to_remove {
+                self.remove_reaction(index);
+            }
+        });
+    }
+
+    fn clear_reactions(&mut self) {
+        let reactions = std::mem::take(&mut self.reactions);
+        self.removed_reactions = Some((0, reactions));
+        self.highlighted_level = None;
+        self.energy_axis_reaction = None;
+        self.scan_reaction = 0;
+        self.fit_reaction = 0;
+        self.search_reaction = 0;
+    }
+
+    // puts the removed reactions back where they were, their parents count from that position
+    fn undo_remove(&mut self) {
+        let Some((index, removed)) = self.removed_reactions.take() else {
+            return;
+        };
+        let index = index.min(self.reactions.len());
+        let count = removed.len();
+        let existing = self.reactions.len();
+
+        for reaction in &mut self.reactions {
+            reaction.parent = match reaction.parent {
+                Some(parent) if parent >= index => Some(parent + count),
+                other => other,
+            };
+        }
+        self.reactions.splice(index..index, removed);
+
+        self.energy_axis_reaction = match self.energy_axis_reaction {
+            Some(axis) if axis >= index => Some(axis + count),
+            other => other,
+        };
+        self.side_panel_reaction = match self.side_panel_reaction {
+            Some(shown) if shown >= index => Some(shown + count),
+            other => other,
+        };
+        for selected in [
+            &mut self.scan_reaction,
+            &mut self.fit_reaction,
+            &mut self.search_reaction,
+        ] {
+            if *selected >= index && existing > 0 {
+                *selected += count;
+            }
+        }
+    }
+
+    fn remove_reaction(&mut self, index: usize) {
+        let reaction = self.reactions.remove(index);
+        self.removed_reactions = Some((index, vec![reaction]));
+        self.highlighted_level = None;
+
+        // secondaries of a removed reaction become primaries
+        for reaction in &mut self.reactions {
+            reaction.parent = match reaction.parent {
+                Some(parent) if parent == index => None,
+                Some(parent) if parent > index => Some(parent - 1),
+                other => other,
+            };
+        }
+
+        // keep the selections pointing at the same reactions
+        self.energy_axis_reaction = match self.energy_axis_reaction {
+            Some(axis) if axis == index => None,
+            Some(axis) if axis > index => Some(axis - 1),
+            other => other,
+        };
+        // the side panel moves on to the next reaction when its own is removed
+        self.side_panel_reaction = match self.side_panel_reaction {
+            Some(shown) if shown > index => Some(shown - 1),
+            other => other,
+        };
+        // the scan, calibration and search fall back to the first reaction when theirs is removed
+        for selected in [
+            &mut self.scan_reaction,
+            &mut self.fit_reaction,
+            &mut self.search_reaction,
+        ] {
+            *selected = match (*selected).cmp(&index) {
+                std::cmp::Ordering::Greater => *selected - 1,
+                std::cmp::Ordering::Equal => 0,
+                std::cmp::Ordering::Less => *selected,
+            };
+        }
+    }
+
+    fn fetch_all_excitation_levels(&mut self, ctx: &egui::Context, refresh: bool) {
+        for reaction in &mut self.reactions {
+            Reaction::populate_reaction_data(reaction, self.mass_evaluation);
+            Reaction::fetch_excitation_levels(reaction, self.data_source, refresh);
+        }
+        self.send_level_requests(ctx);
+    }
+
+    // Sends the levels reactions queued to one background fetch per dataset, isotopes already
+    // on their way aren't asked for twice
+    fn send_level_requests(&mut self, ctx: &egui::Context) {
+        let mut requests: Vec<(String, String)> = Vec::new();
+        for reaction in &self.reactions {
+            let queued = [
+                (
+                    reaction.levels_queued,
+                    &reaction.resid_data,
+                    reaction.levels_dataset(self.data_source),
+                ),
+                (reaction.ejectile_levels_queued, &reaction.ejectile_data, ""),
+            ];
+            for (queued, data, dataset) in queued {
+                let Some(data) = data.as_ref().filter(|_| queued) else {
+                    continue;
+                };
+                let request = (data.isotope.clone(), dataset.to_string());
+                if !requests.contains(&request) && !self.pending_fetches.contains(&request) {
+                    requests.push(request);
+                }
+            }
+        }
+        if requests.is_empty() {
+            return;
+        }
+
+        if self.pending_fetches.is_empty() {
+            self.fetch_total = 0;
+        }
+        self.fetch_total += requests.len();
+        self.pending_fetches.extend(requests.iter().cloned());
+
+        let mut datasets = requests
+            .iter()
+            .map(|(_, dataset)| dataset.clone())
+            .collect::<Vec<_>>();
+        datasets.sort();
+        datasets.dedup();
+        for dataset in datasets {
+            let isotopes = requests
+                .iter()
+                .filter(|(_, requested)| *requested == dataset)
+                .map(|(isotope, _)| isotope.clone())
+                .collect();
+            let fetched_levels = Arc::clone(&self.fetched_levels);
+            let ctx = ctx.clone();
+            let batch_dataset = dataset.clone();
+            self.data_source
+                .get_levels_batch(isotopes, &dataset, move |isotope, levels| {
+                    fetched_levels
+                        .lock()
+                        .unwrap()
+                        .push((isotope, batch_dataset.clone(), levels));
+                    ctx.request_repaint();
+                });
+        }
+    }
+
+    // hands finished lookups to every reaction waiting on that isotope and dataset
+    fn receive_fetched_levels(&mut self) {
+        let fetched = std::mem::take(&mut *self.fetched_levels.lock().unwrap());
+
+        for (isotope, dataset, levels) in fetched {
+            self.pending_fetches
+                .retain(|pending| *pending != (isotope.clone(), dataset.clone()));
+
+            if let Err(error) = &levels {
+                log::error!("No excitation levels found for {}: {}", isotope, error);
+            }
+
+            for reaction in &mut self.reactions {
+                let has_isotope = |data: &Option<NuclearData>| {
+                    data.as_ref().map(|data| &data.isotope) == Some(&isotope)
+                };
+
+                if reaction.levels_queued
+                    && has_isotope(&reaction.resid_data)
+                    && reaction.levels_dataset(self.data_source) == dataset
+                {
+                    reaction.levels_queued = false;
+                    match &levels {
+                        Ok(levels) => {
+                            reaction.excitation_levels = levels.clone();
+                            reaction.levels_isotope = isotope.clone();
+                            reaction.levels_source = reaction.levels_provenance(self.data_source);
+                            reaction.levels_fetched = true;
+                            reaction.levels_fetched_at = now_utc();
+                        }
+                        Err(error) => reaction.fetch_error = Some(error.clone()),
+                    }
+                }
+
+                if reaction.ejectile_levels_queued
+                    && has_isotope(&reaction.ejectile_data)
+                    && dataset.is_empty()
+                {
+                    reaction.ejectile_levels_queued = false;
+                    match &levels {
+                        Ok(levels) => reaction.ejectile_levels = levels.clone(),
+                        Err(error) => reaction.ejectile_levels_error = Some(error.clone()),
+                    }
+                }
+            }
+        }
+    }
+
+    fn calculate_rho_for_all_reactions(&mut self) {
+        for reaction in &mut self.reactions {
+            reaction.calculate_rho(
+                self.mass_evaluation,
+                self.beam_energy,
+                self.magnetic_field,
+                self.sps_angle,
+            );
+        }
+    }
+
+    // Everything a calculation depends on, compared between frames to spot a change. Cheaper to
+    // keep than a copy of each reaction, and a hash of the energies stands in for the levels.
+    fn calculation_inputs(&self) -> String {
+        let mut inputs = format!(
+            "{} {} {} {:?} {}",
+            self.beam_energy,
+            self.magnetic_field,
+            self.sps_angle,
+            self.mass_evaluation,
+            self.ame2020_loads
+        );
+        for reaction in &self.reactions {
+            let mut levels = std::collections::hash_map::DefaultHasher::new();
+            for level in &reaction.excitation_levels {
+                level.energy.to_bits().hash(&mut levels);
+            }
+            inputs.push_str(&format!(
+                "|{:?} {:?} {:?} {:?} {} {:x} {:?} {} {:?}",
+                [
+                    (reaction.target_z, reaction.target_a),
+                    (reaction.projectile_z, reaction.projectile_a),
+                    (reaction.ejectile_z, reaction.ejectile_a),
+                    (reaction.resid_z, reaction.resid_a),
+                ],
+                [
+                    reaction.target_mass_override,
+                    reaction.projectile_mass_override,
+                    reaction.ejectile_mass_override,
+                    reaction.resid_mass_override,
+                ],
+                (reaction.level_filter_min, reaction.level_filter_max),
+                reaction.isomer,
+                reaction.inverse_kinematics,
+                levels.finish(),
+                reaction.additional_excitation_levels,
+                reaction.resid_data.is_some(),
+                reaction.reaction_identifier,
+            ));
+        }
+        inputs
+    }
+
+    // Debounced so dragging a value recalculates once it settles rather than on every pixel
+    fn auto_calculate(&mut self, ctx: &egui::Context) {
+        const SETTLE_TIME: f64 = 0.25; // seconds
+
+        if !self.auto_calc {
+            self.auto_calc_changed = None;
+            return;
+        }
+
+        let (time, dragging) = ctx.input(|i| (i.time, i.pointer.any_down()));
+        let inputs = self.calculation_inputs();
+        if inputs != self.auto_calc_inputs {
+            self.auto_calc_inputs = inputs;
+            self.auto_calc_changed = Some(time);
+        }
+
+        let Some(changed) = self.auto_calc_changed else {
+            return;
+        };
+        if dragging || time - changed < SETTLE_TIME {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(SETTLE_TIME));
+            return;
+        }
+
+        self.auto_calc_changed = None;
+        self.calculate_rho_for_all_reactions();
+    }
+
+    // Every calculated level across the reactions, sorted by the clicked header.
+    // Clicking a row highlights its bar on the plot.
+    fn results_table_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some((index, excitation)) = self.highlighted_level {
+            let field = self.reactions.get(index).and_then(|reaction| {
+                reaction.field_to_center_level(
+                    excitation,
+                    self.beam_energy,
+                    self.sps_angle,
+                    (self.rho_min + self.rho_max) / 2.0,
+                )
+            });
+            ui.horizontal(|ui| {
+                match field {
+                    Some(field) => {
+                        if ui
+                            .button(format!(
+                                "Center {:.*} MeV",
+                                self.energy_decimals, excitation
+                            ))
+                            .on_hover_text(format!(
+                                "Set the field to {:.4} {}, the picked level lands in the middle of the acceptance",
+                                self.field_unit.convert(field),
+                                self.field_unit.label()
+                            ))
+                            .clicked()
+                        {
+                            self.magnetic_field = field;
+                            self.calculate_rho_for_all_reactions();
+                        }
+                    }
+                    None => {
+                        ui.colored_label(
+                            Color32::from_rgb(255, 165, 0),
+                            format!(
+                                "The picked level can't be centered below {} {}",
+                                self.field_unit.convert(MAX_FIELD),
+                                self.field_unit.label()
+                            ),
+                        );
+                    }
+                }
+            });
+        }
+
+        let (rho_min, rho_max) = (self.rho_min, self.rho_max);
+        let inside = |value: &RhoValue| (rho_min..=rho_max).contains(&value.rho);
+
+        let mut rows = self
+            .reactions
+            .iter()
+            .enumerate()
+            .flat_map(|(index, reaction)| {
+                reaction.rho_values.iter().map(move |value| (index, value))
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|(a_index, a), (b_index, b)| {
+            let ordering = match self.results_sort {
+                ResultsColumn::Reaction => a_index.cmp(b_index),
+                ResultsColumn::Energy => a.excitation.total_cmp(&b.excitation),
+                ResultsColumn::Jpi => a.jpi.cmp(&b.jpi),
+                ResultsColumn::Rho => a.rho.total_cmp(&b.rho),
+                ResultsColumn::Brho => a.brho.total_cmp(&b.brho),
+                ResultsColumn::Acceptance => inside(a).cmp(&inside(b)),
+            };
+            if self.results_ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+
+        let columns = [
+            (ResultsColumn::Reaction, "Reaction".to_string()),
+            (ResultsColumn::Energy, "E (MeV)".to_string()),
+            (ResultsColumn::Jpi, "Jπ".to_string()),
+            (
+                ResultsColumn::Rho,
+                format!("Rho ({})", self.rho_unit.label()),
+            ),
+            (
+                ResultsColumn::Brho,
+                format!("Bρ ({} {})", self.field_unit.label(), self.rho_unit.label()),
+            ),
+            (ResultsColumn::Acceptance, "Acceptance".to_string()),
+        ];
+
+        let mut sort_by: Option<ResultsColumn> = None;
+        let mut clicked: Option<(usize, f64)> = None;
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .sense(egui::Sense::click())
+            .columns(Column::auto().resizable(true), columns.len())
+            .header(20.0, |mut header| {
+                for (column, label) in &columns {
+                    header.col(|ui| {
+                        let arrow = match (self.results_sort == *column, self.results_ascending) {
+                            (false, _) => "",
+                            (true, true) => " ⏶",
+                            (true, false) => " ⏷",
+                        };
+                        if ui
+                            .add(egui::Button::new(format!("{}{}", label, arrow)).frame(false))
+                            .clicked()
+                        {
+                            sort_by = Some(*column);
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, rows.len(), |mut row| {
+                    let (index, value) = rows[row.index()];
+                    let reaction = &self.reactions[index];
+                    row.set_selected(self.highlighted_level == Some((index, value.excitation)));
+
+                    row.col(|ui| {
+                        ui.colored_label(reaction.color, &reaction.reaction_identifier);
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.*}", self.energy_decimals, value.excitation));
+                    });
+                    row.col(|ui| {
+                        ui.label(&value.jpi);
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.4}", self.rho_unit.convert(value.rho)));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!(
+                            "{:.4}",
+                            brho_in(value.brho, self.field_unit, self.rho_unit)
+                        ));
+                    });
+                    row.col(|ui| {
+                        if inside(value) {
+                            ui.label("in");
+                        } else {
+                            ui.colored_label(Color32::from_rgb(255, 165, 0), "out");
+                        }
+                    });
+
+                    if row.response().clicked() {
+                        clicked = Some((index, value.excitation));
+                    }
+                });
+            });
+
+        if let Some(column) = sort_by {
+            if self.results_sort == column {
+                self.results_ascending = !self.results_ascending;
+            } else {
+                self.results_sort = column;
+                self.results_ascending = true;
+            }
+        }
+
+        // a second click on the same row clears the highlight
+        if let Some(level) = clicked {
+            self.highlighted_level = (self.highlighted_level != Some(level)).then_some(level);
+        }
+    }
+
+    // a tab per reaction, a column each gets too wide past a handful of reactions
+    fn excitation_levels_side_ui(&mut self, ui: &mut egui::Ui) {
+        // stays on a tab while there are no reactions, so the first one added is shown
+        if self
+            .side_panel_reaction
+            .is_some_and(|index| index >= self.reactions.len())
+            && !self.reactions.is_empty()
+        {
+            self.side_panel_reaction = Some(self.reactions.len() - 1);
+        }
+
+        egui::ScrollArea::horizontal()
+            .id_source("Excitation Levels Tabs")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.side_panel_reaction, None, "All")
+                        .on_hover_text("Every reaction side by side");
+                    for (index, reaction) in self.reactions.iter().enumerate() {
+                        let text = egui::RichText::new(format!("{}", index)).color(reaction.color);
+                        ui.selectable_value(&mut self.side_panel_reaction, Some(index), text)
+                            .on_hover_text(&reaction.reaction_identifier);
+                    }
+                });
+            });
+        ui.separator();
+
+        let levels_ui = |ui: &mut egui::Ui, index: usize, reaction: &mut Reaction| {
+            reaction.excitation_levels_ui(
+                ui,
+                index,
+                &LevelsUiOptions {
+                    data_source: self.data_source,
+                    acceptance: (self.rho_min, self.rho_max),
+                    show_cross_sections: self.yields.enabled,
+                    energy_decimals: self.energy_decimals,
+                    rho_unit: self.rho_unit,
+                },
+            );
+        };
+
+        if let Some(index) = self.side_panel_reaction {
+            if let Some(reaction) = self.reactions.get_mut(index) {
+                levels_ui(ui, index, reaction);
+            }
+            return;
+        }
+
+        let height = ui.available_height();
+        egui::ScrollArea::horizontal()
+            .id_source("Excitation Levels Columns")
+            .show(ui, |ui| {
+                TableBuilder::new(ui)
+                    .columns(Column::auto().resizable(true), self.reactions.len())
+                    .body(|mut body| {
+                        body.row(height, |mut row| {
+                            for (index, reaction) in self.reactions.iter_mut().enumerate() {
+                                row.col(|ui| levels_ui(ui, index, reaction));
+                            }
+                        });
+                    });
+            });
+    }
+
+    // Ticks along the top of the plot at evenly spaced excitation energies of the reference reaction.
+    // The rho <-> energy mapping is nonlinear so the ticks are placed by evaluating rho at each energy.
+    fn draw_energy_axis(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        reaction: &Reaction,
+        x_range: (f64, f64),
+        y_top: f64,
+    ) {
+        let orientation = self.plot_orientation;
+        let (tick_anchor, title_anchor) = match orientation {
+            PlotOrientation::RhoAlongX => (egui::Align2::CENTER_TOP, egui::Align2::LEFT_TOP),
+            PlotOrientation::RhoAlongY => (egui::Align2::RIGHT_CENTER, egui::Align2::RIGHT_BOTTOM),
+        };
+
+        let energy_at = |rho: f64| {
+            reaction.excitation_at_rho(rho, self.beam_energy, self.magnetic_field, self.sps_angle)
+        };
+
+        // rho falls with energy, so the right edge of the plot is the lowest energy
+        let (Some(e_low), Some(e_high)) = (energy_at(x_range.1), energy_at(x_range.0)) else {
+            return;
+        };
+        let e_low = e_low.max(0.0);
+        if e_high <= e_low {
+            return;
+        }
+
+        let rough_step = (e_high - e_low) / 8.0;
+        let magnitude = 10f64.powf(rough_step.log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| *step >= rough_step)
+            .unwrap_or(10.0 * magnitude);
+
+        let decimals = (-step.log10()).ceil().max(0.0) as usize;
+        let color = reaction.color;
+        for tick in (e_low / step).ceil() as i64..=(e_high / step).floor() as i64 {
+            let energy = tick as f64 * step;
+            if let Some(rho) = reaction.rho_at(
+                energy,
+                self.beam_energy,
+                self.magnetic_field,
+                self.sps_angle,
+            ) {
+                plot_ui.line(
+                    Line::new(PlotPoints::new(vec![
+                        orientation.point(rho, y_top),
+                        orientation.point(rho, y_top - 0.15),
+                    ]))
+                    .color(color),
+                );
+                plot_ui.text(
+                    Text::new(
+                        orientation.point(rho, y_top - 0.2).into(),
+                        format!("{:.*}", decimals, energy),
+                    )
+                    .anchor(tick_anchor)
+                    .color(color),
+                );
+            }
+        }
+
+        plot_ui.text(
+            Text::new(
+                orientation.point(x_range.0, y_top).into(),
+                format!("{} E (MeV)", reaction.reaction_identifier),
+            )
+            .anchor(title_anchor)
+            .color(color),
+        );
+    }
+
+    // maps the colors to each J present in the calculated levels
+    fn spin_legend_ui(&self, ui: &mut egui::Ui) {
+        let mut spins: Vec<u32> = self
+            .reactions
+            .iter()
+            .flat_map(|reaction| reaction.rho_values.iter().filter_map(RhoValue::two_j))
+            .collect();
+        spins.sort_unstable();
+        spins.dedup();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("J: ");
+            for two_j in spins {
+                ui.colored_label(spin_color(two_j), format!("■ {}", spin_label(two_j)));
+            }
+            ui.label("(unknown J in reaction color)");
+        });
+    }
+
+    // smallest and largest calculated rho over every reaction
+    fn rho_extent(&self) -> Option<(f64, f64)> {
+        self.reactions
+            .iter()
+            .flat_map(|reaction| reaction.rho_values.iter().map(|value| value.rho))
+            .fold(None, |extent, rho| match extent {
+                Some((min, max)) => Some((rho.min(min), rho.max(max))),
+                None => Some((rho, rho)),
+            })
+    }
+
+    // Rho (cm) at the current field of the levels another field setting puts on the focal plane,
+    // rho scales as 1/B so its bars land inside the acceptance when these do
+    fn field_setting_acceptance(&self, field: f64) -> Option<(f64, f64)> {
+        if field <= 0.0 || self.magnetic_field <= 0.0 {
+            return None;
+        }
+        let scale = field / self.magnetic_field;
+        Some((self.rho_min * scale, self.rho_max * scale))
+    }
+
+    // (index, baseline, secondary) of each reaction on the focal plane plot
+    fn reaction_rows(&self) -> Vec<(usize, f64, bool)> {
+        let mut y_value = 0.25;
+        let mut rows = Vec::new();
+        for (position, (index, secondary)) in
+            reaction_order(&self.reactions).into_iter().enumerate()
+        {
+            if position > 0 {
+                y_value += if secondary {
+                    self.reaction_spacing / 2.0
+                } else {
+                    self.reaction_spacing
+                };
+            }
+            rows.push((index, y_value, secondary));
+        }
+        rows
+    }
+
+    // baselines of the compared reactions, on the row of the reaction they match or above the rest
+    fn comparison_rows(&self, rows: &[(usize, f64, bool)]) -> Vec<f64> {
+        let mut next_row = rows
+            .last()
+            .map_or(0.25, |(_, y, _)| y + self.reaction_spacing);
+        self.comparison
+            .iter()
+            .map(|compared| {
+                let matching = rows.iter().find(|(index, _, _)| {
+                    self.reactions[*index].reaction_identifier == compared.reaction_identifier
+                });
+                match matching {
+                    Some((_, y_value, _)) => *y_value,
+                    None => {
+                        next_row += self.reaction_spacing;
+                        next_row - self.reaction_spacing
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn plot(&mut self, ui: &mut egui::Ui) {
+        match self.plot_mode {
+            PlotMode::FocalPlane => {}
+            PlotMode::AngleScan => return self.angle_scan_plot(ui),
+            PlotMode::BeamEnergyScan => return self.beam_energy_scan_plot(ui),
+        }
+
+        let orientation = self.plot_orientation;
+
+        // legend clicks are read back from the plot memory into each reaction's `visible`
+        let plot_id = ui.make_persistent_id("SPS Plot");
+        let hidden = self
+            .reactions
+            .iter()
+            .enumerate()
+            .filter(|(_, reaction)| !reaction.visible)
+            .map(|(index, reaction)| legend_name(index, reaction));
+
+        // only the rho coordinate is meaningful on hover
+        let plot = Plot::new("SPS Plot")
+            .id(plot_id)
+            .show_x(orientation == PlotOrientation::RhoAlongX)
+            .show_y(orientation == PlotOrientation::RhoAlongY)
+            .allow_boxed_zoom(!self.lock_bounds)
+            .allow_drag(!self.lock_bounds)
+            .allow_scroll(!self.lock_bounds)
+            .legend(Legend::default().hidden_items(hidden));
+
+        // which frame the kinematics assume, the identifiers name the beam of inverse reactions
+        let inverse = self
+            .reactions
+            .iter()
+            .filter(|reaction| reaction.inverse_kinematics)
+            .count();
+        let frame = if inverse == 0 {
+            "normal kinematics"
+        } else if inverse == self.reactions.len() {
+            "inverse kinematics"
+        } else {
+            "normal and inverse kinematics"
+        };
+        let rho_label = format!("Rho ({}), {}", self.rho_unit.label(), frame);
+        let plot = match orientation {
+            PlotOrientation::RhoAlongX => plot
+                .x_axis_label(rho_label)
+                .x_axis_formatter(rho_tick_formatter(self.rho_unit)),
+            PlotOrientation::RhoAlongY => plot
+                .y_axis_label(rho_label)
+                .y_axis_formatter(rho_tick_formatter(self.rho_unit)),
+        };
+
+        let auto_fit = std::mem::take(&mut self.auto_fit_requested);
+
+        if self.color_by_spin {
+            self.spin_legend_ui(ui);
+        }
+
+        // the overview strip takes the right edge, or the bottom when the rows run sideways
+        const OVERVIEW_SIZE: f32 = 24.0;
+        let available = ui.available_rect_before_wrap();
+        let (plot_rect, overview_rect) = match (self.show_overview, orientation) {
+            (false, _) => (available, None),
+            (true, PlotOrientation::RhoAlongX) => {
+                let split = available.right() - OVERVIEW_SIZE;
+                let (plot_rect, overview_rect) = available.split_left_right_at_x(split - 4.0);
+                (plot_rect, Some(overview_rect.with_min_x(split)))
+            }
+            (true, PlotOrientation::RhoAlongY) => {
+                let split = available.bottom() - OVERVIEW_SIZE;
+                let (plot_rect, overview_rect) = available.split_top_bottom_at_y(split - 4.0);
+                (plot_rect, Some(overview_rect.with_min_y(split)))
+            }
+        };
+        let overview_jump = self.overview_jump.take();
+        let mut y_extent = (-1.0, 0.0);
+        let mut crosshair: Option<String> = None;
+
+        let mut plot_area = ui.child_ui(plot_rect, *ui.layout());
+        let response = plot.show(&mut plot_area, |plot_ui| {
+            let x_range = (self.rho_min - 5.0, self.rho_max + 5.0);
+            let rows = self.reaction_rows();
+            let comparison_rows = self.comparison_rows(&rows);
+            let y_top = rows
+                .iter()
+                .map(|(_, y, _)| *y)
+                .chain(comparison_rows.iter().copied())
+                .fold(0.25 - self.reaction_spacing, f64::max)
+                + self.reaction_spacing
+                + 0.75;
+            y_extent.1 = y_top;
+
+            // shade the acceptance first so it sits behind the bars
+            plot_ui.polygon(
+                Polygon::new(PlotPoints::new(vec![
+                    orientation.point(self.rho_min, -1.0),
+                    orientation.point(self.rho_max, -1.0),
+                    orientation.point(self.rho_max, y_top),
+                    orientation.point(self.rho_min, y_top),
+                ]))
+                .fill_color(self.acceptance_color)
+                .stroke(Stroke::NONE),
+            );
+            for field in &self.field_settings {
+                if let Some((rho_min, rho_max)) = self.field_setting_acceptance(*field) {
+                    plot_ui.polygon(
+                        Polygon::new(PlotPoints::new(vec![
+                            orientation.point(rho_min, -1.0),
+                            orientation.point(rho_max, -1.0),
+                            orientation.point(rho_max, y_top),
+                            orientation.point(rho_min, y_top),
+                        ]))
+                        .fill_color(self.acceptance_color.gamma_multiply(0.5))
+                        .stroke(Stroke::NONE),
+                    );
+                }
+            }
+
+            if self.show_spectrum {
+                self.draw_spectrum(plot_ui, y_top);
+            }
+
+            // plots the rho values
+            for rho in [self.rho_min, self.rho_max] {
+                match orientation {
+                    PlotOrientation::RhoAlongX => {
+                        plot_ui.vline(VLine::new(rho).color(Color32::RED))
+                    }
+                    PlotOrientation::RhoAlongY => {
+                        plot_ui.hline(HLine::new(rho).color(Color32::RED))
+                    }
+                }
+            }
+
+            let mut options = DrawOptions {
+                show_peak_labels: self.show_peak_labels,
+                ground_state_only: self.ground_state_only,
+                energy_decimals: self.energy_decimals,
+                orientation,
+                acceptance: (self.rho_min, self.rho_max),
+                search_window: self.search_window(),
+                bar_height: self.bar_height,
+                bar_width: self.bar_width,
+                color_by_spin: self.color_by_spin,
+                rho_resolution: self.rho_resolution,
+                magnetic_field: self.magnetic_field,
+                field_settings: self.field_settings.clone(),
+                field_unit: self.field_unit,
+                rho_unit: self.rho_unit,
+                highlighted_excitation: None,
+                outside_acceptance: self.outside_acceptance,
+                yields: self.max_counts().map(|max| (self.yields, max)),
+                legend_name: String::new(),
+            };
+            for &(index, y_value, secondary) in &rows {
+                options.legend_name = legend_name(index, &self.reactions[index]);
+                options.highlighted_excitation = self
+                    .highlighted_level
+                    .filter(|(reaction, _)| *reaction == index)
+                    .map(|(_, excitation)| excitation);
+                // secondaries sit in half height rows tucked under their parent
+                options.bar_height = if secondary {
+                    self.bar_height / 2.0
+                } else {
+                    self.bar_height
+                };
+                self.reactions[index].draw(plot_ui, y_value, &options);
+            }
+
+            options.highlighted_excitation = None;
+            options.bar_height = self.bar_height;
+            for (reaction, y_value) in self.comparison.iter().zip(comparison_rows) {
+                let muted = Reaction {
+                    color: faded(reaction.color),
+                    reaction_identifier: format!("{} (compared)", reaction.reaction_identifier),
+                    visible: true,
+                    ..reaction.clone()
+                };
+                options.legend_name = muted.reaction_identifier.clone();
+                muted.draw(plot_ui, y_value, &options);
+            }
+
+            if let Some(reaction) = self
+                .energy_axis_reaction
+                .and_then(|index| self.reactions.get(index))
+            {
+                self.draw_energy_axis(plot_ui, reaction, x_range, y_top);
+            }
+
+            if self.show_crosshair && plot_ui.response().hovered() {
+                if let Some(pointer) = plot_ui.pointer_coordinate() {
+                    crosshair = Some(self.draw_crosshair(plot_ui, [pointer.x, pointer.y], &rows));
+                }
+            }
+
+            if self.lock_bounds {
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                    orientation.point(x_range.0, -1.0),
+                    orientation.point(x_range.1, y_top),
+                ));
+            } else if auto_fit {
+                let (min, max) = self.rho_extent().unwrap_or(x_range);
+                let padding = ((max - min) * 0.05).max(0.5);
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                    orientation.point(min - padding, -1.0),
+                    orientation.point(max + padding, y_top),
+                ));
+            } else if let Some(center) = overview_jump {
+                // keep the rho range and the zoom, only move along the stack
+                let bounds = plot_ui.plot_bounds();
+                let axis = orientation.offset_axis();
+                let (mut min, mut max) = (bounds.min(), bounds.max());
+                // from the whole stack zoom in to a few rows around the click
+                let full_span = y_top + 1.0;
+                let span = max[axis] - min[axis];
+                let half_span = if span >= full_span {
+                    (2.0 * self.reaction_spacing).min(full_span / 2.0)
+                } else {
+                    span / 2.0
+                };
+                let center = center.clamp(-1.0 + half_span, y_top - half_span);
+                min[axis] = center - half_span;
+                max[axis] = center + half_span;
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max(min, max));
+            }
+        });
+        ui.advance_cursor_after_rect(available);
+
+        if let Some(rect) = overview_rect {
+            self.overview_ui(ui, rect, y_extent, response.transform.bounds());
+        }
+
+        if let (Some(text), Some(pointer)) = (crosshair, response.response.hover_pos()) {
+            crosshair_readout(ui, pointer, text);
+        }
+
+        if response.response.hovered() {
+            self.keyboard_stepping(ui);
+        }
+        self.draw_step_overlay(ui, response.response.rect);
+
+        if let Some(memory) = PlotMemory::load(ui.ctx(), plot_id) {
+            for (index, reaction) in self.reactions.iter_mut().enumerate() {
+                reaction.visible = !memory.hidden_items.contains(&legend_name(index, reaction));
+            }
+        }
+
+        self.plot_rect = Some(response.response.rect);
+    }
+
+    // every reaction row squeezed into `rect` with the visible part of the stack outlined, clicking or
+    // dragging moves the view there. Uses its own bounds instead of the plot's so it always shows all rows.
+    fn overview_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        (bottom, top): (f64, f64),
+        view: &PlotBounds,
+    ) {
+        let orientation = self.plot_orientation;
+        let axis = orientation.offset_axis();
+        // stacking offset to the screen, the stack grows up or to the right
+        let to_screen = |offset: f64| -> f32 {
+            let fraction = ((offset - bottom) / (top - bottom)).clamp(0.0, 1.0) as f32;
+            match orientation {
+                PlotOrientation::RhoAlongX => egui::lerp(rect.bottom()..=rect.top(), fraction),
+                PlotOrientation::RhoAlongY => egui::lerp(rect.left()..=rect.right(), fraction),
+            }
+        };
+
+        let response = ui.interact(
+            rect,
+            ui.make_persistent_id("SPS Plot overview"),
+            egui::Sense::click_and_drag(),
+        );
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+        for (index, y_value, _) in self.reaction_rows() {
+            let reaction = &self.reactions[index];
+            let color = if reaction.visible {
+                reaction.color
+            } else {
+                faded(reaction.color)
+            };
+            let position = to_screen(y_value);
+            let stroke = Stroke::new(2.0, color);
+            match orientation {
+                PlotOrientation::RhoAlongX => painter.hline(rect.x_range(), position, stroke),
+                PlotOrientation::RhoAlongY => painter.vline(position, rect.y_range(), stroke),
+            };
+        }
+
+        let (start, end) = (to_screen(view.min()[axis]), to_screen(view.max()[axis]));
+        let viewport = match orientation {
+            PlotOrientation::RhoAlongX => egui::Rect::from_x_y_ranges(rect.x_range(), end..=start),
+            PlotOrientation::RhoAlongY => egui::Rect::from_x_y_ranges(start..=end, rect.y_range()),
+        };
+        painter.rect_stroke(
+            viewport,
+            2.0,
+            Stroke::new(1.5, ui.visuals().strong_text_color()),
+        );
+
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let (position, from, to) = match orientation {
+                PlotOrientation::RhoAlongX => (pointer.y, rect.bottom(), rect.top()),
+                PlotOrientation::RhoAlongY => (pointer.x, rect.left(), rect.right()),
+            };
+            let fraction = ((position - from) / (to - from)).clamp(0.0, 1.0) as f64;
+            self.overview_jump = Some(bottom + fraction * (top - bottom));
+            // a locked view can't move
+            self.lock_bounds = false;
+            ui.ctx().request_repaint();
+        }
+
+        response.on_hover_text("Click or drag to move the view along the reactions");
+    }
+
+    // Lines through the cursor and the readout for it, the excitation is of the energy axis reaction
+    // if there is one, otherwise of the row nearest the cursor
+    fn draw_crosshair(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        pointer: [f64; 2],
+        rows: &[(usize, f64, bool)],
+    ) -> String {
+        let orientation = self.plot_orientation;
+        let axis = orientation.offset_axis();
+        let (rho, offset) = (pointer[1 - axis], pointer[axis]);
+
+        let color = Color32::GRAY;
+        let style = LineStyle::Dashed { length: 6.0 };
+        match orientation {
+            PlotOrientation::RhoAlongX => {
+                plot_ui.vline(VLine::new(rho).color(color).style(style));
+                plot_ui.hline(HLine::new(offset).color(color).style(style));
+            }
+            PlotOrientation::RhoAlongY => {
+                plot_ui.hline(HLine::new(rho).color(color).style(style));
+                plot_ui.vline(VLine::new(offset).color(color).style(style));
+            }
+        }
+
+        let mut text = format!(
+            "ρ = {:.3} {}",
+            self.rho_unit.convert(rho),
+            self.rho_unit.label()
+        );
+
+        let reference = self
+            .energy_axis_reaction
+            .filter(|index| *index < self.reactions.len())
+            .or_else(|| {
+                rows.iter()
+                    .filter(|(index, _, _)| self.reactions[*index].visible)
+                    .min_by(|a, b| (a.1 - offset).abs().total_cmp(&(b.1 - offset).abs()))
+                    .map(|(index, _, _)| *index)
+            });
+        if let Some(reaction) = reference.map(|index| &self.reactions[index]) {
+            let excitation = reaction.excitation_at_rho(
+                rho,
+                self.beam_energy,
+                self.magnetic_field,
+                self.sps_angle,
+            );
+            text.push_str(&format!("\n{}: ", reaction.reaction_identifier));
+            match excitation {
+                Some(excitation) => {
+                    text.push_str(&format!("{:.*} MeV", self.energy_decimals, excitation))
+                }
+                None => text.push_str("out of reach"),
+            }
+        }
+
+        text
+    }
+
+    // arrow keys step the beam energy (left/right) and field (up/down) while the plot is hovered
+    fn keyboard_stepping(&mut self, ui: &egui::Ui) {
+        let (beam_steps, field_steps, time) = ui.input(|i| {
+            let presses = |key| {
+                i.events
+                    .iter()
+                    .filter(|event| {
+                        matches!(event, egui::Event::Key { key: k, pressed: true, .. } if *k == key)
+                    })
+                    .count() as f64
+            };
+            (
+                presses(egui::Key::ArrowRight) - presses(egui::Key::ArrowLeft),
+                presses(egui::Key::ArrowUp) - presses(egui::Key::ArrowDown),
+                i.time,
+            )
+        });
+
+        if beam_steps == 0.0 && field_steps == 0.0 {
+            return;
+        }
+
+        self.beam_energy = (self.beam_energy + beam_steps * self.beam_energy_step).max(0.0);
+        self.magnetic_field =
+            (self.magnetic_field + field_steps * self.field_step).clamp(0.0, MAX_FIELD);
+        self.last_step_time = Some(time);
+        self.calculate_rho_for_all_reactions();
+    }
+
+    // shows the stepped values for a moment after the last key press
+    fn draw_step_overlay(&mut self, ui: &egui::Ui, rect: egui::Rect) {
+        const SHOW_FOR: f64 = 1.5; // seconds
+
+        let Some(last_step_time) = self.last_step_time else {
+            return;
+        };
+        if ui.input(|i| i.time) - last_step_time > SHOW_FOR {
+            self.last_step_time = None;
+            return;
+        }
+
+        ui.painter().text(
+            rect.center_top() + egui::vec2(0.0, 10.0),
+            egui::Align2::CENTER_TOP,
+            format!(
+                "Beam {:.3} MeV   Field {:.4} {}",
+                self.beam_energy,
+                self.field_unit.convert(self.magnetic_field),
+                self.field_unit.label()
+            ),
+            egui::FontId::proportional(18.0),
+            ui.visuals().strong_text_color(),
+        );
+        ui.ctx().request_repaint();
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        self.auto_calculate(ui.ctx());
+
+        egui::TopBottomPanel::top("sps_plot_top_panel").show_inside(ui, |ui| {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                self.sps_settings_ui(ui);
+            });
+        });
+
+        egui::TopBottomPanel::bottom("sps_plot_bottom_panel")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                self.reactions_ui(ui);
+            });
+
+        egui::SidePanel::left("sps_plot_side_panel").show_animated_inside(
+            ui,
+            self.side_panel,
+            |ui| {
+                self.excitation_levels_side_ui(ui);
+            },
+        );
+
+        egui::SidePanel::right("sps_plot_results_panel").show_animated_inside(
+            ui,
+            self.results_panel,
+            |ui| {
+                self.results_table_ui(ui);
+            },
+        );
+
+        egui::CentralPanel::default().show_inside(ui, |ui| {
+            self.plot(ui);
+        });
+    }
+}
+
+impl App for SPSPlotApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        self.receive_fetched_levels();
+
+        if self.window {
+            egui::Window::new("SPS Plot")
+                .max_height(900.0)
+                .show(ctx, |ui| {
+                    self.ui(ui);
+                });
+        } else {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                self.ui(ui);
+            });
+        }
+        self.send_level_requests(ctx);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.export_png(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 12C(d,p)13C with the AME2016 masses and four low lying levels
+    fn d_p_reaction() -> Reaction {
+        let mut reaction = Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED);
+        Reaction::populate_reaction_data(&mut reaction, MassEvaluation::Ame2016);
+        reaction.additional_excitation_levels = unnamed(&[0.0, 3.089, 3.685, 3.854]);
+        reaction
+    }
+
+    fn rho_values_at(sps_angle: f64) -> Vec<RhoValue> {
+        let mut reaction = d_p_reaction();
+        reaction.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, sps_angle);
+        reaction.rho_values
+    }
+
+    // AME2016 nuclear masses (MeV)
+    fn nucleus(z: u32, a: u32) -> NuclearData {
+        let mass = match (z, a) {
+            (1, 1) => 938.2720748528063,
+            (1, 2) => 1875.6129290331007,
+            (1, 3) => 2808.9211198317603,
+            (6, 12) => 11174.86323534,
+            (0, 1) => 939.56541955,
+            (6, 13) => 12109.482346972707,
+            (7, 13) => 12111.19181881,
+            (14, 26) => 24204.551661258007,
+            (14, 28) => 26053.18808588079,
+            _ => unreachable!("no test mass for Z = {}, A = {}", z, a),
+        };
+        NuclearData::with_mass(z, a, mass)
+    }
+
+    // Reference rho values are the exact relativistic two-body solution, computed separately from
+    // the app with the AME2016 masses of `nucleus`, the residual mass raised by the excitation:
+    //   s = (E1 + m2)² - p1²,  A = (s + m3² - m4²) / 2
+    //   (E1 + m2) E3 - p1 p3 cos θ = A, solved for the forward p3
+    //   rho (cm) = p3 / (0.299792458 q B), p3 in MeV/c, B in kG, q = Z of the fully stripped ejectile
+    // The app uses nonrelativistic kinematics for the ejectile energy, which differs from it by under
+    // 0.01 cm for these reactions, well inside the SE-SPS resolution.
+    const RHO_TOLERANCE: f64 = 0.05; // cm
+
+    fn assert_rho(
+        nuclei: [(u32, u32); 4],
+        beam_energy: f64,
+        sps_angle: f64,
+        magnetic_field: f64,
+        expected: &[(f64, f64)], // (excitation, rho)
+    ) {
+        let [target, projectile, ejectile, resid] = nuclei.map(|(z, a)| nucleus(z, a));
+        let mut reaction = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        reaction.additional_excitation_levels = expected
+            .iter()
+            .map(|(ex, _)| (String::new(), *ex))
+            .collect();
+
+        reaction.calculate_rho(
+            MassEvaluation::Ame2016,
+            beam_energy,
+            magnetic_field,
+            sps_angle,
+        );
+        assert_eq!(reaction.calculation_error, None);
+
+        for (value, (excitation, rho)) in reaction.rho_values.iter().zip(expected) {
+            assert!(
+                (value.rho - rho).abs() < RHO_TOLERANCE,
+                "{} MeV: rho = {} cm, expected {} cm",
+                excitation,
+                value.rho,
+                rho
+            );
+        }
+    }
+
+    #[test]
+    fn elastic_scattering_matches_reference() {
+        // 12C(p,p)12C, ground state and the 4.439 MeV 2+: 16 MeV, 35°, 8.7 kG
+        assert_rho(
+            [(6, 12), (1, 1), (1, 1), (6, 12)],
+            16.0,
+            35.0,
+            8.7,
+            &[(0.0, 65.6952), (4.439, 55.5368)],
+        );
+    }
+
+    #[test]
+    fn elastic_has_no_q_value() {
+        let mut reaction = Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED);
+        reaction.ejectile_z = reaction.projectile_z;
+        reaction.ejectile_a = reaction.projectile_a;
+        Reaction::populate_reaction_data(&mut reaction, MassEvaluation::Ame2016);
+
+        assert!(reaction.is_elastic());
+        let q_value = reaction.nuclei().expect("12C(d,d) masses").q_value();
+        assert!(q_value.abs() < 1e-9, "Q = {} MeV", q_value);
+    }
+
+    #[test]
+    fn d_p_matches_reference() {
+        // 12C(d,p)13C, ground state and the 3.089 MeV 1/2+: 16 MeV, 35°, 8.7 kG
+        assert_rho(
+            [(6, 12), (1, 2), (1, 1), (6, 13)],
+            16.0,
+            35.0,
+            8.7,
+            &[(0.0, 70.5859), (3.089, 64.0738)],
+        );
+    }
+
+    #[test]
+    fn p_t_matches_reference() {
+        // 28Si(p,t)26Si, ground state and the 1.797 MeV 2+: 35 MeV, 20°, 9.5 kG
+        assert_rho(
+            [(14, 28), (1, 1), (1, 3), (14, 26)],
+            35.0,
+            20.0,
+            9.5,
+            &[(0.0, 94.3078), (1.797, 87.4810)],
+        );
+    }
+
+    #[test]
+    fn endothermic_reactions_are_flagged_below_threshold() {
+        let [target, projectile, ejectile, resid] =
+            [(14, 28), (1, 1), (1, 3), (14, 26)].map(|(z, a)| nucleus(z, a));
+        let p_t = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        let threshold = p_t.nuclei().unwrap().threshold(0.0);
+
+        let (q_value, flagged_threshold) = p_t.below_threshold(threshold - 1.0).unwrap();
+        assert!(q_value < 0.0);
+        assert_eq!(flagged_threshold, threshold);
+        assert_eq!(p_t.below_threshold(threshold + 1.0), None);
+
+        // exothermic reactions run at any energy
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let d_p = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        assert_eq!(d_p.below_threshold(0.1), None);
+    }
+
+    #[test]
+    fn level_statistics_only_count_the_acceptance() {
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let mut reaction = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        reaction.additional_excitation_levels = unnamed(&[0.0, 3.089, 3.685, 3.854, 6.864]);
+        reaction.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, 35.0);
+
+        // everything but 6.864 MeV, which sits at lower rho
+        let cut = reaction.rho_at(6.0, 16.0, 8.7, 35.0).unwrap();
+        let (count, density, spacing) = reaction.level_statistics((cut, 1000.0)).unwrap();
+        assert_eq!(count, 4);
+        assert!((spacing - 3.854 / 3.0).abs() < 1e-9, "{} MeV", spacing);
+        assert!((density * spacing - 1.0).abs() < 1e-12);
+
+        assert_eq!(reaction.level_statistics((0.0, 1.0)), None);
+    }
+
+    #[test]
+    fn momentum_acceptance_is_the_spread_in_rho() {
+        let acceptance = momentum_acceptance(69.0, 87.0);
+        assert!((acceptance - 18.0 / 78.0).abs() < 1e-12);
+
+        // same relative window wherever it sits
+        assert!((momentum_acceptance(138.0, 174.0) - acceptance).abs() < 1e-12);
+        assert_eq!(momentum_acceptance(80.0, 80.0), 0.0);
+    }
+
+    #[test]
+    fn imported_levels_replace_the_bundled_ones() {
+        use super::super::excitation_levels_nndc::{import_levels, ExcitationLevels};
+
+        let rows = [
+            ("12C", vec![0.0, 4.43891]),
+            ("C12", vec![0.0]),
+            ("12c", vec![0.0]),
+        ]
+        .map(|(isotope, levels)| (isotope.to_string(), levels));
+        let (imported, skipped) = import_levels(rows.to_vec()).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(skipped, ["C12", "12c"]);
+
+        // built with its own table, the one the app imported is left alone
+        let imported = Some(Arc::new(imported));
+        let energies = |levels: &ExcitationLevels, isotope| {
+            levels
+                .get(isotope)
+                .map(|levels| levels.iter().map(|level| level.energy).collect::<Vec<_>>())
+        };
+        let with_import = ExcitationLevels::with_imported(imported);
+        assert_eq!(energies(&with_import, "12C"), Some(vec![0.0, 4.43891]));
+        // isotopes missing from the import still come from the bundled levels
+        assert!(energies(&with_import, "13C").is_some_and(|levels| levels.len() > 2));
+
+        let bundled = ExcitationLevels::with_imported(None);
+        assert_ne!(energies(&bundled, "12C"), Some(vec![0.0, 4.43891]));
+        assert!(import_levels(vec![("bad".to_string(), vec![])]).is_err());
+    }
+
+    #[test]
+    fn loaded_projects_bring_their_imported_levels() {
+        use super::super::excitation_levels_nndc::ExcitationLevels;
+
+        let dir = std::env::temp_dir().join(format!("sps_plot_project_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let levels_csv = dir.join("excitation_levels.csv");
+        std::fs::write(&levels_csv, "isotope,levels\n209Bi,\"0,0.8966\"\n").unwrap();
+
+        let saved = SPSPlotApp {
+            levels_csv_path: Some(levels_csv.clone()),
+            ..Default::default()
+        };
+        let project = dir.join("project.json");
+        super::super::project::save_project(&saved, &project).unwrap();
+
+        let mut app = SPSPlotApp {
+            pending_fetches: vec![("13C".to_string(), String::new())],
+            ..Default::default()
+        };
+        app.open_project(super::super::project::load_project(&project).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(app.levels_csv_path, Some(levels_csv));
+        let energies = ExcitationLevels::new()
+            .get("209Bi")
+            .map(|levels| levels.iter().map(|level| level.energy).collect::<Vec<_>>());
+        assert_eq!(energies, Some(vec![0.0, 0.8966]));
+        // the fetch sent before loading still lands
+        assert_eq!(app.pending_fetches.len(), 1);
+    }
+
+    #[test]
+    fn additional_levels_load_with_or_without_names() {
+        let old: Reaction =
+            serde_json::from_str(r#"{"additional_excitation_levels": [0.0, 3.089]}"#).unwrap();
+        assert_eq!(old.additional_excitation_levels, unnamed(&[0.0, 3.089]));
+
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let mut reaction = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        reaction.additional_excitation_levels = vec![
+            ("suspected 2+ state".to_string(), 4.2),
+            (String::new(), 3.089),
+        ];
+        let saved = serde_json::to_string(&reaction).unwrap();
+        let loaded: Reaction = serde_json::from_str(&saved).unwrap();
+        assert_eq!(
+            loaded.additional_excitation_levels,
+            reaction.additional_excitation_levels
+        );
+
+        reaction.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, 35.0);
+        let names = reaction
+            .rho_values
+            .iter()
+            .map(|value| (value.excitation, value.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, [(4.2, "suspected 2+ state"), (3.089, "")]);
+    }
+
+    #[test]
+    fn calculation_inputs_ignore_the_results() {
+        let mut app = SPSPlotApp {
+            reactions: vec![Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED)],
+            ..Default::default()
+        };
+        let inputs = app.calculation_inputs();
+
+        app.calculate_rho_for_all_reactions();
+        assert_eq!(app.calculation_inputs(), inputs);
+
+        app.beam_energy += 0.1;
+        assert_ne!(app.calculation_inputs(), inputs);
+        app.beam_energy -= 0.1;
+        app.reactions[0].level_filter_max = 5.0;
+        assert_ne!(app.calculation_inputs(), inputs);
+
+        // same number of levels at other energies, or other masses under the same evaluation
+        app.reactions[0].excitation_levels = vec![Level::new(0.0), Level::new(3.089)];
+        let inputs = app.calculation_inputs();
+        app.reactions[0].excitation_levels[1].energy += 0.001;
+        assert_ne!(app.calculation_inputs(), inputs);
+        let inputs = app.calculation_inputs();
+        app.ame2020_loads += 1;
+        assert_ne!(app.calculation_inputs(), inputs);
+    }
+
+    #[test]
+    fn duplicate_reactions_are_found_and_merged() {
+        let d_p = |notes: &str, levels: Vec<f64>| Reaction {
+            notes: notes.to_string(),
+            additional_excitation_levels: unnamed(&levels),
+            ..Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED)
+        };
+        let mut reactions = vec![
+            d_p("primary", vec![0.0]),
+            Reaction::new(Color32::RED),
+            Reaction::new(Color32::RED),
+            d_p("", vec![0.0, 3.089]),
+            Reaction {
+                copy: true,
+                ..d_p("", vec![])
+            },
+        ];
+        reactions[0].must_see = vec![0.0];
+        reactions[3].must_see = vec![0.0, 3.089];
+        reactions[3].ejectile_levels = vec![Level::new(0.0)];
+
+        let duplicates = (0..reactions.len())
+            .map(|index| duplicate_of(&reactions, index))
+            .collect::<Vec<_>>();
+        // empty reactions aren't duplicates of each other, and copies are meant to be
+        assert_eq!(duplicates, [None, None, None, Some(0), None]);
+
+        let duplicate = reactions.remove(3);
+        reactions[0].merge(duplicate);
+        assert_eq!(
+            reactions[0].additional_excitation_levels,
+            unnamed(&[0.0, 3.089])
+        );
+        assert_eq!(reactions[0].notes, "primary");
+        assert_eq!(reactions[0].must_see, [0.0, 3.089]);
+        assert_eq!(reactions[0].ejectile_levels.len(), 1);
+    }
+
+    fn unnamed(levels: &[f64]) -> Vec<(String, f64)> {
+        levels.iter().map(|level| (String::new(), *level)).collect()
+    }
+
+    fn d_p_nuclei() -> [NuclearData; 4] {
+        [(6, 12), (1, 2), (1, 1), (6, 13)].map(|(z, a)| nucleus(z, a))
+    }
+
+    #[test]
+    fn long_lived_states_are_isomers() {
+        let level = |energy: f64, half_life: &str| Level {
+            half_life: half_life.to_string(),
+            ..Level::new(energy)
+        };
+
+        let ground_state = level(0.0, "7.17×105 y 24");
+        assert_eq!(
+            ground_state.half_life_seconds(),
+            Some(7.17e5 * 365.25 * 86_400.0)
         );
+        assert!(!ground_state.is_isomer());
 
-        info!("Reaction: {:?}", reaction);
+        assert!(level(0.228, "6.3460 s 6").is_isomer());
+        assert!(!level(0.417, "1.25 ns 3").is_isomer());
+        assert!(!level(7.0, "40 keV 5").is_isomer());
+        assert!(!level(1.0, "").is_isomer());
     }
 
-    fn fetch_excitation_levels(reaction: &mut Reaction) {
-        let isotope = reaction
-            .resid_data
-            .as_ref()
-            .map_or("None", |data| &data.isotope);
-        if isotope == "None" {
-            log::error!(
-                "No isotope found for reaction: {}",
-                reaction.reaction_identifier
-            );
-        }
+    #[test]
+    fn tandem_beam_energy_follows_the_terminal() {
+        // deuterons at 8 MV
+        let tandem = TandemSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(tandem.beam_energy(), 16.0);
 
-        let excitation_levels = ExcitationLevels::new();
+        let oxygen = TandemSettings {
+            terminal_voltage: 8.5,
+            charge_state: 6,
+            injection_energy: 50.0,
+            ..tandem
+        };
+        assert!((oxygen.beam_energy() - 59.55).abs() < 1e-12);
+    }
 
-        if let Some(levels) = excitation_levels.get(isotope) {
-            log::info!("Excitation levels for {}: {:?}", isotope, levels);
-            reaction.excitation_levels = levels;
+    #[test]
+    fn yield_estimate_scales_with_the_inputs() {
+        let settings = YieldSettings {
+            enabled: true,
+            beam_current: 1.0,
+            target_thickness: 100.0,
+            run_time: 1.0,
+            solid_angle: 1.0,
+            cross_section: 1.0,
+        };
 
-            log::info!(
-                "Excitation levels for {}: {:?}",
-                isotope,
-                reaction.excitation_levels.clone()
-            );
-        } else {
-            log::error!("No excitation levels found for {}.", isotope);
-        }
+        // 2.25e13 protons on 5.02e18 12C/cm² at 1 mb/sr into 1 msr
+        let counts = settings.counts(1, 12, 1.0);
+        assert!((counts - 112.8).abs() < 0.1, "{} counts", counts);
+
+        assert!((settings.counts(1, 12, 2.0) - 2.0 * counts).abs() < 1e-9);
+        // a doubly charged beam at the same electrical current is half the particles
+        assert!((settings.counts(2, 12, 1.0) - counts / 2.0).abs() < 1e-9);
+        assert_eq!(settings.counts(0, 12, 1.0), 0.0);
     }
-}
 
-#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-pub struct SPSPlotApp {
-    sps_angle: f64,
-    beam_energy: f64,
-    magnetic_field: f64,
-    rho_min: f64,
-    rho_max: f64,
-    reactions: Vec<Reaction>,
-    reaction_data: HashMap<String, Vec<(f64, f64)>>,
-    side_panel: bool,
-    window: bool,
-}
+    #[test]
+    fn merged_levels_count_together_toward_the_tallest_bar() {
+        let mut app = SPSPlotApp {
+            reactions: vec![d_p_reaction()],
+            rho_resolution: 0.0,
+            ..Default::default()
+        };
+        app.yields.enabled = true;
+        app.calculate_rho_for_all_reactions();
 
-impl Default for SPSPlotApp {
-    fn default() -> Self {
-        Self {
-            sps_angle: 35.0,
-            beam_energy: 16.0,
-            magnetic_field: 8.7,
-            rho_min: 69.0,
-            rho_max: 87.0,
-            reactions: Vec::new(),
-            reaction_data: HashMap::new(),
-            side_panel: false,
-            window: false,
+        let level = app.max_counts().unwrap();
+        // 3.685 and 3.854 MeV are under a cm apart, one bar with both levels' counts
+        app.rho_resolution = 1.0;
+        assert!((app.max_counts().unwrap() - 2.0 * level).abs() < 1e-9 * level);
+        app.reactions[0].visible = false;
+        assert_eq!(app.max_counts(), None);
+    }
+
+    #[test]
+    fn copied_rho_values_keep_the_full_energy() {
+        let mut reaction = d_p_reaction();
+        reaction.additional_excitation_levels = unnamed(&[3.08446]);
+        reaction.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, 35.0);
+
+        let tsv = reaction.rho_values_tsv(RhoUnit::Centimeter);
+        assert!(
+            tsv.lines().nth(1).unwrap().starts_with("3.08446\t"),
+            "{}",
+            tsv
+        );
+    }
+
+    #[test]
+    fn display_units_round_trip() {
+        for unit in [FieldUnit::Kilogauss, FieldUnit::Tesla] {
+            assert!((unit.to_kilogauss(unit.convert(8.7)) - 8.7).abs() < 1e-12);
         }
+        for unit in [RhoUnit::Centimeter, RhoUnit::Meter] {
+            assert!((unit.to_centimeter(unit.convert(69.0)) - 69.0).abs() < 1e-12);
+        }
+        assert_eq!(FieldUnit::Tesla.convert(10.0), 1.0);
+        assert_eq!(RhoUnit::Meter.convert(87.0), 0.87);
     }
-}
 
-impl SPSPlotApp {
-    pub fn new(cc: &eframe::CreationContext<'_>, window: bool) -> Self {
-        let mut app = Self {
-            sps_angle: 35.0,     // degree
-            beam_energy: 16.0,   // MeV
-            magnetic_field: 8.7, // kG
-            rho_min: 69.0,
-            rho_max: 87.0,
-            reactions: Vec::new(),
-            reaction_data: HashMap::new(),
-            side_panel: false,
-            window,
+    #[test]
+    fn neutral_ejectile_and_zero_field_have_no_rho() {
+        let reaction = |ejectile: (u32, u32), resid: (u32, u32)| {
+            let [target, projectile, ejectile, resid] =
+                [(6, 12), (1, 2), ejectile, resid].map(|(z, a)| nucleus(z, a));
+            let mut reaction =
+                Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+            reaction.additional_excitation_levels = unnamed(&[0.0]);
+            reaction
         };
 
-        if let Some(storage) = cc.storage {
-            app = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
-        }
+        // 12C(d,n)13N
+        let mut d_n = reaction((0, 1), (7, 13));
+        d_n.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, 35.0);
+        assert!(d_n.rho_values.is_empty());
+        assert!(d_n
+            .calculation_error
+            .unwrap()
+            .starts_with("Neutral ejectile"));
 
-        app
+        let mut d_p = reaction((1, 1), (6, 13));
+        d_p.calculate_rho(MassEvaluation::Ame2016, 16.0, 0.0, 35.0);
+        assert!(d_p.rho_values.is_empty());
+        assert_eq!(
+            d_p.calculation_error.as_deref(),
+            Some("Set a nonzero magnetic field")
+        );
+        assert_eq!(d_p.rho_at(0.0, 16.0, 0.0, 35.0), None);
     }
 
-    fn sps_settings_ui(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            egui::global_dark_light_mode_switch(ui);
+    #[test]
+    fn notes_close_every_csv_row() {
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let mut reaction = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        reaction.additional_excitation_levels = unnamed(&[0.0, 3.089]);
+        reaction.notes = "primary channel, \"13C\"".to_string();
+        reaction.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, 35.0);
 
-            ui.heading("SE-SPS Settings");
-        });
+        let csv = super::super::export::rho_values_csv(&[reaction], false);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert!(lines[0].ends_with(",notes"));
+        assert_eq!(lines.len(), 3);
+        for line in &lines[1..] {
+            assert!(line.ends_with(r#","primary channel, ""13C""""#), "{}", line);
+        }
+    }
 
-        ui.horizontal(|ui| {
-            ui.label("SPS Angle: ")
-                .on_hover_text("SE-SPS's angle currently limited to 60°");
-            ui.add(
-                egui::DragValue::new(&mut self.sps_angle)
-                    .suffix("°")
-                    .clamp_range(0.0..=60.0),
-            );
+    #[test]
+    fn latex_table_has_only_the_chosen_columns() {
+        use super::super::export::{reactions_latex, LatexTable};
 
-            ui.label("Beam Energy: ");
-            ui.add(
-                egui::DragValue::new(&mut self.beam_energy)
-                    .suffix(" MeV")
-                    .clamp_range(0.0..=f64::MAX),
-            );
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let mut d_p = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        d_p.reaction_identifier = "12C(d,p)13C".to_string();
+        let mut table = LatexTable {
+            reactions: std::slice::from_ref(&d_p),
+            columns: LatexColumns::default(),
+            beam_energy: 16.0,
+            magnetic_field: 8.7,
+            sps_angle: 35.0,
+            rho_min: 69.0,
+        };
 
-            ui.label("Magnetic Field: ");
-            ui.add(
-                egui::DragValue::new(&mut self.magnetic_field)
-                    .suffix(" kG")
-                    .clamp_range(0.0..=17.0),
-            );
+        let latex = reactions_latex(&table);
+        assert!(latex.contains(r"\begin{tabular}{lrrr}"), "{}", latex);
+        let row = latex
+            .lines()
+            .find(|line| line.contains("$^{13}$C"))
+            .expect("a row for 12C(d,p)13C");
+        let cells = row
+            .trim_end_matches(r" \\")
+            .split(" & ")
+            .collect::<Vec<_>>();
+        assert_eq!(cells[0], "$^{12}$C(d,p)$^{13}$C");
+        assert_eq!(cells[1], "2.722"); // Q of 12C(d,p) in MeV
+        let rho = d_p.rho_at(0.0, 16.0, 8.7, 35.0).unwrap();
+        assert_eq!(cells[2], format!("{:.2}", rho));
+        let max_excitation = cells[3].parse::<f64>().unwrap();
+        let edge = d_p.rho_at(max_excitation, 16.0, 8.7, 35.0).unwrap();
+        assert!((edge - 69.0).abs() < 0.1, "{} cm", edge);
 
-            ui.label("Rho Min: ")
-                .on_hover_text("SE-SPS Rho Min is usually 69.0");
-            ui.add(
-                egui::DragValue::new(&mut self.rho_min)
-                    .suffix(" cm")
-                    .clamp_range(0.0..=f64::MAX),
-            );
+        table.columns.ground_state_rho = false;
+        table.columns.max_excitation = false;
+        let latex = reactions_latex(&table);
+        assert!(latex.contains(r"\begin{tabular}{lr}"), "{}", latex);
+        assert!(!latex.contains(r"\rho"));
+    }
 
-            ui.label("Rho Max: ")
-                .on_hover_text("SE-SPS Rho Max is usually 87.0");
-            ui.add(
-                egui::DragValue::new(&mut self.rho_max)
-                    .suffix(" cm")
-                    .clamp_range(0.0..=f64::MAX),
-            );
+    #[test]
+    fn only_levels_inside_the_window_are_calculated() {
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let mut reaction = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        reaction.excitation_levels = (0..1000).map(|i| Level::new(i as f64 * 0.01)).collect();
+        reaction.additional_excitation_levels = unnamed(&[9.5]);
+        reaction.level_filter_min = 1.0;
+        reaction.level_filter_max = 2.0;
+        reaction.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, 35.0);
 
-            ui.separator();
+        assert_eq!(reaction.rho_values.len(), 102); // 1.00 to 2.00 MeV and the additional level
+        for value in &reaction.rho_values {
+            let rho = reaction.rho_at(value.excitation, 16.0, 8.7, 35.0).unwrap();
+            assert_eq!(value.rho, rho);
+            assert!((value.brho / 8.7 - rho).abs() < 1e-12);
+        }
+        assert_eq!(reaction.rho_values.last().unwrap().excitation, 9.5);
+    }
 
-            if ui.button("Calculate").clicked() {
-                self.calculate_rho_for_all_reactions();
-            }
+    #[test]
+    fn refreshing_levels_keeps_the_reaction() {
+        let mut reaction = Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED);
+        Reaction::populate_reaction_data(&mut reaction, MassEvaluation::Ame2016);
+        let identifier = reaction.reaction_identifier.clone();
+        reaction.excitation_levels.clear();
 
-            ui.separator();
+        // edited but not fetched yet, a refresh still reads the last residual
+        reaction.ejectile_a = 3;
+        Reaction::fetch_excitation_levels(&mut reaction, DataSource::Bundled, true);
 
-            ui.checkbox(&mut self.side_panel, "Show Exciation Levels");
-        });
+        assert!(!reaction.excitation_levels.is_empty());
+        assert_eq!(reaction.levels_isotope, "13C");
+        assert_eq!(reaction.reaction_identifier, identifier);
+        assert_eq!((reaction.resid_z, reaction.resid_a), (6, 13));
+        assert_eq!(reaction.ejectile_a, 3);
+        assert_eq!(reaction.levels_fetched_at, BUNDLED_LEVELS_RETRIEVED);
     }
 
-    fn reactions_ui(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            ui.heading("Reactions");
+    #[test]
+    fn nndc_levels_are_fetched_in_the_background() {
+        let mut reaction = Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED);
+        Reaction::populate_reaction_data(&mut reaction, MassEvaluation::Ame2016);
+        reaction.excitation_levels.clear();
+        Reaction::fetch_excitation_levels(&mut reaction, DataSource::Nndc, true);
+        Reaction::fetch_ejectile_levels(&mut reaction, DataSource::Nndc);
+        assert!(reaction.levels_queued && reaction.ejectile_levels_queued);
+        assert!(reaction.excitation_levels.is_empty());
 
-            ui.separator();
+        let mut app = SPSPlotApp {
+            reactions: vec![reaction],
+            data_source: DataSource::Nndc,
+            ..Default::default()
+        };
+        app.pending_fetches = vec![("13C".to_string(), String::new())];
+        app.fetched_levels.lock().unwrap().extend([
+            (
+                "13C".to_string(),
+                "12C(d,p)".to_string(),
+                Ok(vec![Level::new(1.0)]),
+            ),
+            (
+                "13C".to_string(),
+                String::new(),
+                Ok(vec![Level::new(0.0), Level::new(3.089)]),
+            ),
+            (
+                "1H".to_string(),
+                String::new(),
+                Err("No levels".to_string()),
+            ),
+        ]);
+        app.receive_fetched_levels();
 
-            if ui.button("Calculate").clicked() {
-                self.calculate_rho_for_all_reactions();
-            }
+        let reaction = &app.reactions[0];
+        assert!(!reaction.levels_queued && !reaction.ejectile_levels_queued);
+        assert_eq!(reaction.excitation_levels.len(), 2); // the adopted levels, not the dataset's
+        assert!(reaction.levels_fetched_at.ends_with(" UTC"));
+        assert_eq!(reaction.ejectile_levels_error.as_deref(), Some("No levels"));
+        assert!(app.pending_fetches.is_empty());
+    }
 
-            ui.separator();
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn svg_stacks_the_reactions_like_the_plot() {
+        use crate::export::{plot_svg, SvgPlot};
 
-            if ui.button("+").clicked() {
-                let colors = [
-                    Color32::from_rgb(120, 47, 64), // go noles!
-                    Color32::from_rgb(206, 184, 136),
-                    Color32::BLUE,
-                    Color32::GREEN,
-                    Color32::YELLOW,
-                    Color32::BROWN,
-                    Color32::DARK_RED,
-                    Color32::RED,
-                    Color32::LIGHT_RED,
-                    Color32::LIGHT_YELLOW,
-                    Color32::KHAKI,
-                    Color32::DARK_GREEN,
-                    Color32::LIGHT_GREEN,
-                    Color32::DARK_BLUE,
-                    Color32::LIGHT_BLUE,
-                ];
+        let bar = |color, parent| Reaction {
+            parent,
+            rho_values: vec![RhoValue {
+                rho: 60.0,
+                ..Default::default()
+            }],
+            ..Reaction::new(color)
+        };
+        // the secondary 2 sits just above its parent 0, below the primary 1 (red is the acceptance)
+        let app = SPSPlotApp {
+            reactions: vec![
+                bar(Color32::YELLOW, None),
+                bar(Color32::GREEN, None),
+                bar(Color32::BLUE, Some(0)),
+            ],
+            ..Default::default()
+        };
+        let svg = plot_svg(&SvgPlot {
+            reactions: &app.reactions,
+            rows: &app.reaction_rows(),
+            rho_range: (40.0, 100.0),
+            acceptance: (app.rho_min, app.rho_max),
+            acceptance_color: app.acceptance_color,
+            bar_height: app.bar_height,
+            reaction_spacing: app.reaction_spacing,
+        });
 
-                // change the default color to be random
-                let index = self.reactions.len();
-                let color = colors[index % colors.len()];
+        let bottom_of = |color: Color32| -> f64 {
+            let stroke = format!(r#"stroke="rgb({},{},{})""#, color.r(), color.g(), color.b());
+            let line = svg.lines().find(|line| line.contains(&stroke)).unwrap();
+            let y1 = line.split("y1=\"").nth(1).unwrap();
+            y1[..y1.find('"').unwrap()].parse().unwrap()
+        };
+        let [primary, other, secondary] =
+            [Color32::YELLOW, Color32::GREEN, Color32::BLUE].map(bottom_of);
+        assert!(
+            primary > secondary && secondary > other,
+            "{} {} {}",
+            primary,
+            secondary,
+            other
+        );
+    }
 
-                self.reactions.push(Reaction::new(color));
-            }
-        });
+    #[test]
+    fn peaks_are_identified_by_rho_or_energy() {
+        let mut app = SPSPlotApp {
+            reactions: vec![d_p_reaction()],
+            show_search: true,
+            search_tolerance: 0.01,
+            ..Default::default()
+        };
+        app.calculate_rho_for_all_reactions();
+        let excitations = |app: &SPSPlotApp| {
+            app.search_matches()
+                .iter()
+                .map(|&(reaction, value, _)| app.reactions[reaction].rho_values[value].excitation)
+                .collect::<Vec<_>>()
+        };
 
-        egui::ScrollArea::both().show(ui, |ui| {
-            ui.separator();
+        app.search_rho = app.reactions[0].rho_values[2].rho;
+        assert_eq!(excitations(&app), [3.685]);
 
-            let mut index_to_remove: Option<usize> = None;
+        // the energy lands on its own level's rho, whatever rho was typed in before
+        app.search_by_energy = true;
+        app.search_energy = 3.089;
+        assert_eq!(excitations(&app), [3.089]);
+        assert!(app.search_matches()[0].2.abs() < 1e-9);
 
-            for (index, reaction) in self.reactions.iter_mut().enumerate() {
-                ui.horizontal(|ui| {
-                    ui.label(format!("Reaction {}", index));
+        app.search_reaction = 1;
+        assert!(app.search_matches().is_empty());
+    }
 
-                    ui.separator();
+    #[test]
+    fn field_settings_shade_the_levels_they_accept() {
+        let mut app = SPSPlotApp {
+            reactions: vec![d_p_reaction()],
+            field_settings: vec![0.0, 8.0],
+            ..Default::default()
+        };
+        app.calculate_rho_for_all_reactions();
 
-                    if ui.button("-").clicked() {
-                        index_to_remove = Some(index);
-                    }
+        assert_eq!(app.field_setting_acceptance(0.0), None);
+        let (rho_min, rho_max) = app.field_setting_acceptance(8.0).unwrap();
+        let scale = app.magnetic_field / 8.0;
+        for value in &app.reactions[0].rho_values {
+            // the faded bar is on the focal plane exactly when the level is in the shaded band
+            assert_eq!(
+                (rho_min..=rho_max).contains(&value.rho),
+                (app.rho_min..=app.rho_max).contains(&(value.rho * scale)),
+            );
+        }
+    }
 
-                    reaction.settings_ui(ui);
-                });
-            }
+    #[test]
+    fn namesakes_get_their_own_legend_entry() {
+        let reaction = Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED);
+        let copy = reaction.clone();
 
-            if let Some(index) = index_to_remove {
-                self.reactions.remove(index);
-            }
-        });
+        assert_ne!(legend_name(0, &reaction), legend_name(1, &copy));
+        assert!(legend_name(1, &copy).contains(&copy.reaction_identifier));
     }
 
-    fn excitation_level_to_rho(
-        reaction: &mut Reaction,
-        beam_energy: f64,
-        magnetic_field: f64,
-        sps_angle: f64,
-    ) {
-        reaction.rho_values.clear();
+    #[test]
+    fn side_panel_keeps_its_reaction_when_an_earlier_one_is_removed() {
+        let mut app = SPSPlotApp {
+            reactions: vec![Reaction::new(Color32::RED); 3],
+            ..Default::default()
+        };
+        assert_eq!(app.side_panel_reaction, Some(0));
 
-        let target = reaction.target_data.as_ref().unwrap();
-        let projectile = reaction.projectile_data.as_ref().unwrap();
-        let ejectile = reaction.ejectile_data.as_ref().unwrap();
-        let resid = reaction.resid_data.as_ref().unwrap();
+        app.side_panel_reaction = Some(2);
+        app.remove_reaction(0);
+        assert_eq!(app.side_panel_reaction, Some(1));
+        app.remove_reaction(1);
+        assert_eq!(app.side_panel_reaction, Some(1)); // moved past the end, the panel clamps it
+        app.side_panel_reaction = None;
+        app.remove_reaction(0);
+        assert_eq!(app.side_panel_reaction, None);
+    }
 
-        let reaction_identifier = format!(
-            "{}({},{}){}",
-            target.isotope, projectile.isotope, ejectile.isotope, resid.isotope
-        );
-        info!("Reaction: {}", reaction_identifier);
+    #[test]
+    fn scan_and_fit_follow_their_reaction_on_removal() {
+        let mut app = SPSPlotApp {
+            reactions: vec![Reaction::new(Color32::RED); 4],
+            scan_reaction: 3,
+            fit_reaction: 1,
+            ..Default::default()
+        };
 
-        let q_value = target.mass + projectile.mass - ejectile.mass - resid.mass;
+        app.remove_reaction(0);
+        assert_eq!((app.scan_reaction, app.fit_reaction), (2, 0));
+        app.remove_reaction(2);
+        assert_eq!((app.scan_reaction, app.fit_reaction), (0, 0));
+    }
 
-        let mut levels = reaction.excitation_levels.clone();
-        for level in reaction.additional_excitation_levels.iter() {
-            levels.push(*level);
-        }
+    #[test]
+    fn cleared_reactions_can_be_restored() {
+        let with_parent = |parent| Reaction {
+            parent,
+            ..Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED)
+        };
+        let mut app = SPSPlotApp {
+            reactions: vec![with_parent(None), with_parent(Some(0))],
+            energy_axis_reaction: Some(1),
+            ..Default::default()
+        };
 
-        log::info!("Excitation levels: {:?}", levels);
+        app.clear_reactions();
+        assert!(app.reactions.is_empty());
+        assert_eq!(app.energy_axis_reaction, None);
 
-        for excitation in levels {
-            // for excitation in &reaction.excitation_levels {
+        app.undo_remove();
+        assert_eq!(app.reactions.len(), 2);
+        assert_eq!(app.reactions[1].parent, Some(0));
+        assert!(app.removed_reactions.is_none());
 
-            let reaction_q_value = q_value - excitation;
-            // let beam_reaction_energy = self.beam_energy; // could put energy loss through target here
-            let beam_reaction_energy = beam_energy; // could put energy loss through target here
+        // a single removal goes back in its place
+        app.remove_reaction(0);
+        app.undo_remove();
+        assert_eq!(app.reactions[0].parent, None);
+        assert_eq!(app.reactions.len(), 2);
+    }
 
-            let _threshold = -reaction_q_value * (ejectile.mass + resid.mass)
-                / (ejectile.mass + resid.mass - projectile.mass);
-            let term1 = (projectile.mass * ejectile.mass * beam_reaction_energy).sqrt()
-                / (ejectile.mass + resid.mass)
-                * (sps_angle * PI / 180.0).cos();
-            let term2 = (beam_reaction_energy * (resid.mass - projectile.mass)
-                + resid.mass * reaction_q_value)
-                / (ejectile.mass + resid.mass);
+    #[test]
+    fn sessions_from_before_spin_parity_still_load() {
+        let old: Reaction = serde_json::from_str(
+            r#"{"excitation_levels": [0.0, 3.089], "rho_values": [[0.0, 75.2], [3.089, 71.9]]}"#,
+        )
+        .unwrap();
+        let energies = old
+            .excitation_levels
+            .iter()
+            .map(|level| level.energy)
+            .collect::<Vec<_>>();
+        assert_eq!(energies, [0.0, 3.089]);
+        assert!(old
+            .excitation_levels
+            .iter()
+            .all(|level| level.jpi.is_empty()));
+        let pairs = old
+            .rho_values
+            .iter()
+            .map(|value| (value.excitation, value.rho))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, [(0.0, 75.2), (3.089, 71.9)]);
 
-            let ke1 = term1 + (term1 * term1 + term2).sqrt();
-            let ke2 = term1 + (term1 * term1 + term2).sqrt();
+        let mut reaction = Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED);
+        Reaction::populate_reaction_data(&mut reaction, MassEvaluation::Ame2016);
+        reaction.excitation_levels = vec![Level {
+            jpi: "1/2-".to_string(),
+            ..Level::new(0.0)
+        }];
+        reaction.calculate_rho(MassEvaluation::Ame2016, 16.0, 8.7, 35.0);
+        let loaded: Reaction =
+            serde_json::from_str(&serde_json::to_string(&reaction).unwrap()).unwrap();
+        assert_eq!(loaded.excitation_levels[0].jpi, "1/2-");
+        assert_eq!(loaded.rho_values[0].jpi, "1/2-");
+        assert_eq!(loaded.rho_values[0].rho, reaction.rho_values[0].rho);
+    }
 
-            let ejectile_energy = if ke1 > 0.0 { ke1 * ke1 } else { ke2 * ke2 };
+    #[test]
+    fn orientations_saved_by_their_bar_names_still_load() {
+        let old: PlotOrientation = serde_json::from_str(r#""Horizontal""#).unwrap();
+        assert_eq!(old, PlotOrientation::RhoAlongY);
+        assert_eq!(old.point(75.0, 1.0), [1.0, 75.0]);
+        let old: PlotOrientation = serde_json::from_str(r#""Vertical""#).unwrap();
+        assert_eq!(old, PlotOrientation::RhoAlongX);
+    }
 
-            // convert ejectile ke to rho
-            let p = (ejectile_energy * (ejectile_energy + 2.0 * ejectile.mass)).sqrt();
-            let qbrho = p / QBRHO2P;
-            let rho = qbrho / (magnetic_field * ejectile.z as f64);
-            info!("Excitation: {}, rho: {}", excitation, rho);
+    #[test]
+    fn secondaries_follow_their_parent() {
+        let with_parent = |parent| Reaction {
+            parent,
+            ..Reaction::new(Color32::RED)
+        };
+        // 3 hangs off 0, 1 off the secondary 3 and 4 off a missing reaction
+        let reactions = [None, Some(3), None, Some(0), Some(7)].map(with_parent);
 
-            reaction.rho_values.push((excitation, rho));
-        }
+        assert_eq!(
+            reaction_order(&reactions),
+            vec![(0, false), (3, true), (1, false), (2, false), (4, false)]
+        );
     }
 
-    fn calculate_rho_for_all_reactions(&mut self) {
-        for reaction in &mut self.reactions {
-            Self::excitation_level_to_rho(
-                reaction,
-                self.beam_energy,
-                self.magnetic_field,
-                self.sps_angle,
-            );
+    #[test]
+    fn field_and_angle_search_fits_the_must_see_levels() {
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let mut reaction = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+        reaction.must_see = vec![0.0, 3.089];
+
+        let mut app = SPSPlotApp {
+            reactions: vec![reaction],
+            ..Default::default()
+        };
+        let best = app.best_field_and_angle().unwrap();
+        assert_eq!((best.inside, best.total), (2, 2));
+        for excitation in [0.0, 3.089] {
+            let rho = app.reactions[0]
+                .rho_at(excitation, app.beam_energy, best.field, best.angle)
+                .unwrap();
+            assert!((app.rho_min..=app.rho_max).contains(&rho), "rho = {}", rho);
         }
-    }
 
-    fn excitation_levels_side_ui(&mut self, ui: &mut egui::Ui) {
-        let height = ui.available_height();
-        TableBuilder::new(ui)
-            .columns(Column::auto().resizable(true), self.reactions.len())
-            .body(|mut body| {
-                body.row(height, |mut row| {
-                    for (index, reaction) in &mut self.reactions.iter_mut().enumerate() {
-                        row.col(|ui| {
-                            reaction.excitation_levels_ui(ui, index);
-                        });
-                    }
-                });
-            });
+        app.reactions[0].must_see.clear();
+        assert_eq!(app.best_field_and_angle(), None);
     }
 
-    fn plot(&mut self, ui: &mut egui::Ui) {
-        let plot = Plot::new("SPS Plot")
-            .show_y(false)
-            .allow_boxed_zoom(false)
-            .allow_drag(false)
-            .allow_scroll(false)
-            .legend(Legend::default());
+    #[test]
+    fn inverse_kinematics_swap_the_beam_and_the_target() {
+        let [carbon, deuteron, proton, resid] =
+            [(6, 12), (1, 2), (1, 1), (6, 13)].map(|(z, a)| nucleus(z, a));
+        // d(12C,p)13C typed as its normal kinematics reaction with the 12C beam ticked as inverse
+        let mut inverse = Reaction::from_nuclei(
+            carbon.clone(),
+            deuteron.clone(),
+            proton.clone(),
+            resid.clone(),
+            Color32::RED,
+        );
+        inverse.inverse_kinematics = true;
+        let beam_target = Reaction::from_nuclei(deuteron, carbon, proton, resid, Color32::RED);
 
-        plot.show(ui, |plot_ui| {
-            // plots the rho values
-            plot_ui.vline(VLine::new(self.rho_min).color(Color32::RED));
-            plot_ui.vline(VLine::new(self.rho_max).color(Color32::RED));
+        for excitation in [0.0, 3.089] {
+            let rho = inverse.rho_at(excitation, 72.0, 12.0, 20.0).unwrap();
+            let expected = beam_target.rho_at(excitation, 72.0, 12.0, 20.0).unwrap();
+            assert!((rho - expected).abs() < 1e-9, "rho = {}", rho);
+        }
 
-            for (index, reaction) in self.reactions.iter_mut().enumerate() {
-                let y_value = index as f64 + 0.25;
-                reaction.draw(plot_ui, y_value);
-            }
+        inverse.inverse_kinematics = false;
+        let normal = inverse.rho_at(0.0, 72.0, 12.0, 20.0).unwrap();
+        inverse.inverse_kinematics = true;
+        assert!(inverse.rho_at(0.0, 72.0, 12.0, 20.0).unwrap() < normal);
 
-            plot_ui.set_plot_bounds(PlotBounds::from_min_max(
-                (self.rho_min - 5.0, -1.0).into(),
-                (self.rho_max + 5.0, self.reactions.len() as f64 + 1.0).into(),
-            ));
-        });
+        let mut reaction = Reaction::from_preset(&REACTION_PRESETS[0], Color32::RED);
+        reaction.inverse_kinematics = true;
+        Reaction::populate_reaction_data(&mut reaction, MassEvaluation::Ame2016);
+        assert_eq!(reaction.reaction_identifier, "2H(12C,1H)13C");
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui) {
-        egui::TopBottomPanel::top("sps_plot_top_panel").show_inside(ui, |ui| {
-            egui::ScrollArea::horizontal().show(ui, |ui| {
-                self.sps_settings_ui(ui);
-            });
-        });
-
-        egui::TopBottomPanel::bottom("sps_plot_bottom_panel")
-            .resizable(true)
-            .show_inside(ui, |ui| {
-                self.reactions_ui(ui);
-            });
+    #[test]
+    fn ejectile_levels_are_kept_apart_from_the_residual() {
+        // 13C knocked out of the target by the deuteron beam
+        let mut reaction = Reaction {
+            target_z: 6,
+            target_a: 13,
+            projectile_z: 1,
+            projectile_a: 2,
+            ejectile_z: 6,
+            ejectile_a: 13,
+            ..Default::default()
+        };
+        Reaction::fetch_ejectile_levels(&mut reaction, DataSource::Bundled);
+        assert!(reaction.ejectile_levels_error.is_some());
 
-        egui::SidePanel::left("sps_plot_side_panel").show_animated_inside(
-            ui,
-            self.side_panel,
-            |ui| {
-                self.excitation_levels_side_ui(ui);
-            },
-        );
+        Reaction::populate_reaction_data(&mut reaction, MassEvaluation::Ame2016);
+        Reaction::fetch_ejectile_levels(&mut reaction, DataSource::Bundled);
 
-        egui::CentralPanel::default().show_inside(ui, |ui| {
-            self.plot(ui);
-        });
+        assert_eq!(reaction.ejectile_levels_error, None);
+        assert!(reaction
+            .ejectile_levels
+            .iter()
+            .any(|level| (level.energy - 3.089).abs() < 1e-6));
+        assert!(reaction.excitation_levels.is_empty());
     }
-}
 
-impl App for SPSPlotApp {
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+    #[test]
+    fn centered_level_lands_on_the_requested_rho() {
+        let [target, projectile, ejectile, resid] = d_p_nuclei();
+        let reaction = Reaction::from_nuclei(target, projectile, ejectile, resid, Color32::RED);
+
+        let field = reaction
+            .field_to_center_level(3.089, 16.0, 35.0, 80.0)
+            .unwrap();
+        let rho = reaction.rho_at(3.089, 16.0, field, 35.0).unwrap();
+        assert!((rho - 80.0).abs() < 1e-9, "rho = {}", rho);
+
+        // far too small a radius for the SPS field
+        assert_eq!(reaction.field_to_center_level(0.0, 16.0, 35.0, 1.0), None);
     }
 
-    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
-        if self.window {
-            egui::Window::new("SPS Plot")
-                .max_height(900.0)
-                .show(ctx, |ui| {
-                    self.ui(ui);
-                });
-        } else {
-            egui::CentralPanel::default().show(ctx, |ui| {
-                for (reaction, data) in &self.reaction_data {
-                    ui.label(format!("{}: {:?}", reaction, data));
-                }
-                self.ui(ui);
-            });
+    #[test]
+    fn rho_values_are_finite_at_the_angle_limits() {
+        for sps_angle in [0.0, MAX_SPS_ANGLE] {
+            let rho_values = rho_values_at(sps_angle);
+            assert_eq!(rho_values.len(), 4);
+            assert!(
+                rho_values.iter().all(|value| value.rho.is_finite()),
+                "NaN rho at {}°",
+                sps_angle
+            );
+        }
+
+        // 12C beam on deuterium, the 13C only goes forward of its maximum angle
+        let [carbon, deuteron, ejectile, proton] =
+            [(6, 12), (1, 2), (6, 13), (1, 1)].map(|(z, a)| nucleus(z, a));
+        let mut inverse = Reaction::from_nuclei(carbon, deuteron, ejectile, proton, Color32::RED);
+        inverse.inverse_kinematics = true;
+        inverse.additional_excitation_levels = unnamed(&[0.0, 0.5, 1.0, 2.0]);
+        for (sps_angle, count) in [(0.0, 4), (MAX_SPS_ANGLE, 0)] {
+            inverse.calculate_rho(MassEvaluation::Ame2016, 72.0, 12.0, sps_angle);
+            assert_eq!(inverse.rho_values.len(), count, "at {}°", sps_angle);
+            assert!(inverse.rho_values.iter().all(|value| value.rho.is_finite()));
         }
     }
 }
@@ -0,0 +1,145 @@
+use super::excitation_levels_nndc::Level;
+
+// ENSDF nuclide id, the mass number right justified in three columns then the element in two, e.g. " 13C "
+fn nucid(isotope: &str) -> Option<String> {
+    let split = isotope.find(|c: char| !c.is_ascii_digit())?;
+    let (mass_number, element) = isotope.split_at(split);
+    if mass_number.is_empty() || mass_number.len() > 3 || element.len() > 2 {
+        return None;
+    }
+    Some(format!(
+        "{:>3}{:<2}",
+        mass_number,
+        element.to_ascii_uppercase()
+    ))
+}
+
+// 1-based inclusive ENSDF columns, short lines are padded with blanks
+fn columns(line: &str, first: usize, last: usize) -> &str {
+    let end = last.min(line.len());
+    line.get(first - 1..end).unwrap_or_default()
+}
+
+// ENSDF uncertainties are in units of the last digit of the value, "3089.443" with "20" is 3089.443(20)
+fn uncertainty(value: &str, digits: &str) -> Option<f64> {
+    let digits = digits.parse::<f64>().ok()?;
+    let decimals = value
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len());
+    Some(digits / 10f64.powi(decimals as i32))
+}
+
+// Level (L) record, columns 10-19 E (keV), 20-21 DE, 22-39 J, 40-49 T and 50-55 DT.
+// Energies relative to an unplaced level, like "1234.5+X", are skipped.
+fn parse_level_record(line: &str) -> Option<Level> {
+    let energy_text = columns(line, 10, 19).trim();
+    let energy = energy_text.parse::<f64>().ok()?;
+
+    let half_life = columns(line, 40, 49).trim().to_ascii_lowercase();
+    let half_life_uncertainty = columns(line, 50, 55).trim();
+
+    Some(Level {
+        energy: energy / 1000.0,
+        uncertainty: uncertainty(energy_text, columns(line, 20, 21).trim())
+            .map(|uncertainty| uncertainty / 1000.0),
+        jpi: columns(line, 22, 39).trim().to_string(),
+        half_life: if half_life_uncertainty.is_empty() {
+            half_life
+        } else {
+            format!("{} {}", half_life, half_life_uncertainty)
+        },
+    })
+}
+
+// Reads the levels of `isotope` (e.g. "13C") from an ENSDF file, which can hold a whole mass chain.
+// Datasets are separated by blank lines and start with an identification record naming them.
+// The adopted levels are used when the file has them, otherwise the first dataset with levels.
+pub fn parse_ensdf_levels(contents: &str, isotope: &str) -> Result<Vec<Level>, String> {
+    let nucid = nucid(isotope).ok_or_else(|| format!("{} is not an isotope name", isotope))?;
+
+    // (dataset id, levels) for every dataset of the isotope
+    let mut datasets: Vec<(String, Vec<Level>)> = Vec::new();
+    let mut in_dataset = false;
+    let mut matches = false;
+
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            in_dataset = false;
+            continue;
+        }
+
+        if !in_dataset {
+            in_dataset = true;
+            matches = line.starts_with(&nucid);
+            if matches {
+                datasets.push((columns(line, 10, 39).trim().to_string(), Vec::new()));
+            }
+            continue;
+        }
+
+        // column 6 marks continuation records and column 7 comments
+        if matches && columns(line, 6, 9) == "  L " {
+            if let (Some(level), Some((_, levels))) =
+                (parse_level_record(line), datasets.last_mut())
+            {
+                levels.push(level);
+            }
+        }
+    }
+
+    let adopted = datasets
+        .iter()
+        .position(|(id, levels)| id.starts_with("ADOPTED LEVELS") && !levels.is_empty());
+    let index = adopted.or_else(|| datasets.iter().position(|(_, levels)| !levels.is_empty()));
+
+    match index {
+        Some(index) => Ok(datasets.swap_remove(index).1),
+        None => Err(format!("No {} level records in the ENSDF file", isotope)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENSDF: &str = " 13N     ADOPTED LEVELS
+ 13N   L 0.0         1/2-              9.965 M   4
+ 13N   L 2364.9    6 1/2+              31.7 KEV  8
+
+ 13C     ADOPTED LEVELS, GAMMAS
+ 13C   L 0.0         1/2-              STABLE
+ 13C  cL E         From 12C(n,G)
+ 13C   L 3089.443  201/2+              1.07 FS   5
+ 13C 2 L XREF=ABC
+ 13C   L 3684.507  193/2-              1.10 FS   +12-9
+ 13C   L 1000.0+X
+
+ 13C     12C(D,P)
+ 13C   L 0.0         1/2-
+ 13C   L 3089        1/2+
+";
+
+    #[test]
+    fn adopted_levels_of_the_isotope_are_read() {
+        let levels = parse_ensdf_levels(ENSDF, "13C").unwrap();
+
+        assert_eq!(levels.len(), 3);
+        for (level, energy) in levels.iter().zip([0.0, 3.089443, 3.684507]) {
+            assert!((level.energy - energy).abs() < 1e-9, "{} MeV", level.energy);
+        }
+        assert_eq!(levels[1].jpi, "1/2+");
+        assert!((levels[1].uncertainty.unwrap() - 0.00002).abs() < 1e-12);
+        assert_eq!(levels[0].half_life, "stable");
+        assert_eq!(levels[1].half_life, "1.07 fs 5");
+    }
+
+    #[test]
+    fn other_isotopes_and_missing_ones_are_told_apart() {
+        let levels = parse_ensdf_levels(ENSDF, "13N").unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[0].half_life_seconds(), Some(9.965 * 60.0));
+
+        assert!(parse_ensdf_levels(ENSDF, "12C").is_err());
+        assert_eq!(nucid("208Pb").as_deref(), Some("208PB"));
+    }
+}
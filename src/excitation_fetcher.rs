@@ -0,0 +1,214 @@
+use super::excitation_levels_nndc::{
+    has_imported_levels, ExcitationLevels, Level, BUNDLED_LEVELS_RETRIEVED,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum DataSource {
+    #[default]
+    Bundled,
+    Nndc,
+}
+
+impl DataSource {
+    // `dataset` names an NNDC evaluation, empty for the adopted levels. The bundled levels are
+    // always the adopted ones.
+    pub fn get_levels(&self, isotope: &str, dataset: &str) -> Result<Vec<Level>, String> {
+        match self {
+            DataSource::Bundled => {
+                let bundled = ExcitationLevels::new();
+                bundled.get(isotope).ok_or_else(|| {
+                    format!(
+                        "No excitation levels found in the bundled data (retrieved {})",
+                        bundled.retrieved
+                    )
+                })
+            }
+            DataSource::Nndc => fetch_nndc_levels(isotope, dataset),
+        }
+    }
+
+    // Where levels from this source came from, recorded with them for the About panel
+    pub fn provenance(&self) -> String {
+        match self {
+            DataSource::Bundled if has_imported_levels() => {
+                "NNDC levels imported from nndc_excitation_level_getter".to_string()
+            }
+            DataSource::Bundled => format!("NNDC levels bundled on {}", BUNDLED_LEVELS_RETRIEVED),
+            #[cfg(not(target_arch = "wasm32"))]
+            DataSource::Nndc => format!("NNDC, fetched {}", today()),
+            #[cfg(target_arch = "wasm32")]
+            DataSource::Nndc => "NNDC".to_string(),
+        }
+    }
+
+    // Looks up several isotopes in `dataset`, NNDC requests run concurrently on a background
+    // thread. `on_result` is called with each isotope as its levels arrive.
+    pub fn get_levels_batch<F>(&self, isotopes: Vec<String>, dataset: &str, on_result: F)
+    where
+        F: Fn(String, Result<Vec<Level>, String>) + Send + Sync + 'static,
+    {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            DataSource::Nndc => fetch_nndc_levels_batch(isotopes, dataset, on_result),
+            _ => {
+                for isotope in isotopes {
+                    let levels = self.get_levels(&isotope, dataset);
+                    on_result(isotope, levels);
+                }
+            }
+        }
+    }
+}
+
+// Parses a user supplied level file with one energy (MeV) per line and an optional Jπ column.
+// Blank lines and '#' comments are ignored, returns the levels and the number of malformed lines.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_levels_csv(contents: &str) -> (Vec<Level>, usize) {
+    let mut levels = Vec::new();
+    let mut skipped = 0;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut columns = line
+            .split(|c: char| c == ',' || c == '\t' || c == ' ')
+            .map(str::trim)
+            .filter(|column| !column.is_empty());
+
+        match columns.next().map(str::parse::<f64>) {
+            Some(Ok(energy)) => levels.push(Level {
+                energy,
+                uncertainty: None,
+                jpi: columns.next().unwrap_or_default().to_string(),
+                half_life: String::new(),
+            }),
+            _ => skipped += 1,
+        }
+    }
+
+    (levels, skipped)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_nndc_levels(isotope: &str, dataset: &str) -> Result<Vec<Level>, String> {
+    use nndc_excitation_level_getter::excitation_fetcher::ExcitationFetcher;
+
+    // kept at NNDC's precision, the app rounds only for display
+    let fetcher = ExcitationFetcher {
+        dataset: dataset.to_string(),
+        precision: None,
+        ..ExcitationFetcher::new()
+    };
+    fetcher.fetch_excitation_levels(isotope);
+
+    if let Some(error) = fetcher.error_message.lock().unwrap().clone() {
+        return Err(error);
+    }
+
+    let levels = fetcher
+        .excitation_levels
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_default();
+
+    Ok(from_nndc_levels(levels))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_seconds() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn today() -> String {
+    utc_date(unix_seconds())
+}
+
+// "YYYY-MM-DD HH:MM UTC" for when levels were fetched, empty on the web where std has no clock
+pub fn now_utc() -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let seconds = unix_seconds();
+        format!(
+            "{} {:02}:{:02} UTC",
+            utc_date(seconds),
+            seconds / 3600 % 24,
+            seconds / 60 % 60
+        )
+    }
+    #[cfg(target_arch = "wasm32")]
+    String::new()
+}
+
+// UTC date as YYYY-MM-DD, converted from days since the epoch (Howard Hinnant's civil_from_days)
+#[cfg(not(target_arch = "wasm32"))]
+fn utc_date(seconds: u64) -> String {
+    let z = (seconds / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153; // March = 0
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// at most this many requests to NNDC are in flight at once
+#[cfg(not(target_arch = "wasm32"))]
+const NNDC_CONCURRENCY: usize = 4;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_nndc_levels_batch<F>(isotopes: Vec<String>, dataset: &str, on_result: F)
+where
+    F: Fn(String, Result<Vec<Level>, String>) + Send + Sync + 'static,
+{
+    use nndc_excitation_level_getter::excitation_fetcher::ExcitationFetcher;
+
+    let dataset = dataset.to_string();
+    std::thread::spawn(move || {
+        let names = isotopes.clone();
+        let fetcher = ExcitationFetcher {
+            dataset,
+            precision: None,
+            ..ExcitationFetcher::new()
+        };
+        fetcher.fetch_many(isotopes, NNDC_CONCURRENCY, move |index, result| {
+            on_result(names[index].clone(), result.map(from_nndc_levels));
+        });
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn from_nndc_levels(
+    levels: Vec<nndc_excitation_level_getter::excitation_fetcher::Level>,
+) -> Vec<Level> {
+    levels
+        .into_iter()
+        .map(|level| Level {
+            energy: level.energy,
+            uncertainty: level.uncertainty,
+            jpi: level.jpi,
+            half_life: level.half_life,
+        })
+        .collect()
+}
+
+// the scraper depends on tokio/reqwest, which are not built for the web
+#[cfg(target_arch = "wasm32")]
+fn fetch_nndc_levels(_isotope: &str, _dataset: &str) -> Result<Vec<Level>, String> {
+    Err("Fetching from NNDC is not supported on the web, use the bundled levels".to_string())
+}
@@ -1,11 +1,132 @@
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::nuclear_data_amdc_2016::{parse_isotope, NuclearData};
+#[cfg(not(target_arch = "wasm32"))]
+use nndc_excitation_level_getter::excitation_fetcher::IsotopeLevels;
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Level {
+    pub energy: f64, // MeV
+    #[serde(default)]
+    pub uncertainty: Option<f64>, // MeV, None when NNDC gives a limit or nothing
+    pub jpi: String, // spin-parity, empty when unknown
+    #[serde(default)]
+    pub half_life: String, // NNDC T1/2 as given, empty when unknown
+}
+
+// NUBASE counts states living at least 100 ns as isomers
+pub const ISOMER_HALF_LIFE: f64 = 100e-9; // s
+
+impl Level {
+    pub fn new(energy: f64) -> Self {
+        Self {
+            energy,
+            uncertainty: None,
+            jpi: String::new(),
+            half_life: String::new(),
+        }
+    }
+
+    // Half-life in seconds from NNDC text like "6.346 s 2" or "7.17×105 y 24" (the exponent loses its
+    // superscript when scraped). None for widths such as "40 keV" and anything unparsable.
+    pub fn half_life_seconds(&self) -> Option<f64> {
+        let text = self.half_life.trim();
+        if text.to_ascii_uppercase().starts_with("STABLE") {
+            return Some(f64::INFINITY);
+        }
+
+        let mut parts = text.split_whitespace();
+        let value = parts
+            .next()?
+            .trim_start_matches(['~', '≈', '>', '<', '≥', '≤'])
+            .replace("×10", "E")
+            .parse::<f64>()
+            .ok()?;
+        let unit = match parts.next()? {
+            "y" => 365.25 * 86_400.0,
+            "d" => 86_400.0,
+            "h" => 3_600.0,
+            "m" | "min" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "μs" | "µs" | "us" => 1e-6,
+            "ns" => 1e-9,
+            "ps" => 1e-12,
+            "fs" => 1e-15,
+            "as" => 1e-18,
+            _ => return None,
+        };
+
+        Some(value * unit)
+    }
+
+    // long-lived excited state, the ground state never counts
+    pub fn is_isomer(&self) -> bool {
+        self.energy > 0.0
+            && self
+                .half_life_seconds()
+                .is_some_and(|half_life| half_life >= ISOMER_HALF_LIFE)
+    }
+}
+
+// date the bundled levels were scraped from NNDC
+pub const BUNDLED_LEVELS_RETRIEVED: &str = "6/20/2024";
+
+// isotope -> levels (MeV) from an excitation_levels.csv rebuilt with nndc_excitation_level_getter,
+// looked up before the bundled levels
+pub type ImportedLevels = HashMap<String, Vec<f64>>;
+
+// the imported levels every lookup uses, set once the app has read the file
+static IMPORTED: RwLock<Option<Arc<ImportedLevels>>> = RwLock::new(None);
+
+// Keeps the getter's (isotope, levels) rows named in the "12C" form reactions look up, returns
+// them with the names skipped
+#[cfg(not(target_arch = "wasm32"))]
+pub fn import_levels(rows: Vec<IsotopeLevels>) -> Result<(ImportedLevels, Vec<String>), String> {
+    let mut imported = HashMap::new();
+    let mut skipped = Vec::new();
+    for (isotope, levels) in rows {
+        let lookup_name = parse_isotope(&isotope)
+            .ok()
+            .map(|(z, a)| NuclearData::with_mass(z, a, 0.0).isotope);
+        if lookup_name.as_deref() == Some(isotope.as_str()) {
+            imported.insert(isotope, levels);
+        } else {
+            skipped.push(isotope);
+        }
+    }
+    if imported.is_empty() {
+        return Err("No isotopes found, expected the excitation_levels.csv written by nndc_excitation_level_getter".to_string());
+    }
+
+    Ok((imported, skipped))
+}
+
+// None goes back to the bundled levels
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_imported_levels(imported: Option<ImportedLevels>) {
+    *IMPORTED.write().unwrap() = imported.map(Arc::new);
+}
+
+pub fn has_imported_levels() -> bool {
+    IMPORTED.read().unwrap().is_some()
+}
 
 pub struct ExcitationLevels {
     levels: Vec<HashMap<&'static str, Vec<f32>>>,
+    imported: Option<Arc<ImportedLevels>>,
+    pub retrieved: &'static str,
 }
 
 impl ExcitationLevels {
+    // the bundled levels with whatever the app has imported
     pub fn new() -> Self {
+        Self::with_imported(IMPORTED.read().unwrap().clone())
+    }
+
+    pub fn with_imported(imported: Option<Arc<ImportedLevels>>) -> Self {
         Self {
             levels: vec![
                 excitation_levels_nndc_1(),
@@ -18,19 +139,38 @@ impl ExcitationLevels {
                 excitation_levels_nndc_8(),
                 excitation_levels_nndc_9(),
             ],
+            imported,
+            retrieved: BUNDLED_LEVELS_RETRIEVED,
         }
     }
 
-    pub fn get(&self, isotope: &str) -> Option<Vec<f64>> {
+    // the bundled and imported levels only carry energies, so Jπ is left empty
+    pub fn get(&self, isotope: &str) -> Option<Vec<Level>> {
+        if let Some(levels) = self
+            .imported
+            .as_ref()
+            .and_then(|imported| imported.get(isotope))
+        {
+            return Some(levels.iter().map(|&x| Level::new(x)).collect());
+        }
+
         for dataset in &self.levels {
             if let Some(levels) = dataset.get(isotope) {
-                return Some(levels.iter().map(|&x| x as f64).collect());
+                return Some(levels.iter().map(|&x| Level::new(x as f64)).collect());
             }
         }
         None
     }
 }
 
+// NNDC NuDat page the bundled levels were scraped from
+pub fn nndc_url(isotope: &str) -> String {
+    format!(
+        "https://www.nndc.bnl.gov/nudat3/getdatasetClassic.jsp?nucleus={}&unc=nds",
+        isotope
+    )
+}
+
 // this is annoying, when compiling to the web there can only be so many items in a function. found this number to be around 450 items execpt in the rare earth region
 // I am sorry if anyone looks at this... i hate it too
 // i couldnt figure out how to get a text file when compiling to the web
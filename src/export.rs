@@ -0,0 +1,367 @@
+use std::path::Path;
+
+use eframe::egui::{Color32, ColorImage};
+
+use super::app::{LatexColumns, Reaction};
+
+// Writes a screenshot region to a PNG, resized by `resize`. Resampling adds pixels, not detail,
+// the SVG export is the one for sharp figures.
+pub fn save_png(image: &ColorImage, resize: f32, path: &Path) -> Result<(), String> {
+    let [width, height] = image.size;
+    let pixels = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_array())
+        .collect::<Vec<u8>>();
+
+    let buffer = image::RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .ok_or_else(|| "Screenshot has an unexpected size".to_string())?;
+
+    let width = ((width as f32) * resize).round().max(1.0) as u32;
+    let height = ((height as f32) * resize).round().max(1.0) as u32;
+    let resized = image::imageops::resize(
+        &buffer,
+        width,
+        height,
+        image::imageops::FilterType::Lanczos3,
+    );
+
+    resized
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// One row per calculated level, p and Bρ are only written when `include_momentum` is set.
+// The reaction's notes close every row.
+pub fn rho_values_csv(reactions: &[Reaction], include_momentum: bool) -> String {
+    let mut header = "reaction,excitation_mev,excitation_unc_mev,jpi,rho_cm,rho_unc_cm".to_string();
+    if include_momentum {
+        header.push_str(",p_mev_c,brho_kg_cm");
+    }
+    header.push_str(",notes");
+
+    let mut lines = vec![header];
+    for reaction in reactions {
+        for value in &reaction.rho_values {
+            let mut line = format!(
+                "{},{},{},{},{},{}",
+                csv_field(&reaction.reaction_identifier),
+                value.excitation,
+                value.uncertainty.map_or(String::new(), |u| u.to_string()),
+                csv_field(&value.jpi),
+                value.rho,
+                value
+                    .rho_uncertainty
+                    .map_or(String::new(), |u| u.to_string()),
+            );
+            if include_momentum {
+                line.push_str(&format!(",{},{}", value.momentum, value.brho));
+            }
+            line.push_str(&format!(",{}", csv_field(&reaction.notes)));
+            lines.push(line);
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// Jπ values like "(1/2,3/2)+" and free-text notes need quoting
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// The reactions and the spectrograph settings their numbers are worked out at
+pub struct LatexTable<'a> {
+    pub reactions: &'a [Reaction],
+    pub columns: LatexColumns,
+    pub beam_energy: f64,    // MeV
+    pub magnetic_field: f64, // kG
+    pub sps_angle: f64,      // degrees
+    pub rho_min: f64,        // cm, the highest excitation reaching the focal plane lands here
+}
+
+// A `tabular` with one row per reaction for proposals and logbooks, numbers that can't be
+// worked out (missing masses, nothing on the focal plane) are written as "--"
+pub fn reactions_latex(table: &LatexTable<'_>) -> String {
+    let columns = table.columns;
+    let mut header = vec!["Reaction"];
+    if columns.q_value {
+        header.push("$Q$ (MeV)");
+    }
+    if columns.ground_state_rho {
+        header.push(r"$\rho_{\mathrm{g.s.}}$ (cm)");
+    }
+    if columns.max_excitation {
+        header.push(r"$E_x^{\mathrm{max}}$ (MeV)");
+    }
+
+    let mut lines = vec![
+        format!(
+            "% {} MeV beam, {} kG, {} deg",
+            table.beam_energy, table.magnetic_field, table.sps_angle
+        ),
+        format!(r"\begin{{tabular}}{{l{}}}", "r".repeat(header.len() - 1)),
+        r"\hline".to_string(),
+        format!(r"{} \\", header.join(" & ")),
+        r"\hline".to_string(),
+    ];
+
+    let number = |value: Option<f64>, decimals: usize| {
+        value.map_or("--".to_string(), |value| format!("{:.*}", decimals, value))
+    };
+    for reaction in table.reactions {
+        let mut row = vec![latex_reaction(&reaction.reaction_identifier)];
+        if columns.q_value {
+            row.push(number(reaction.nuclei().map(|nuclei| nuclei.q_value()), 3));
+        }
+        if columns.ground_state_rho {
+            let rho = reaction.rho_at(
+                0.0,
+                table.beam_energy,
+                table.magnetic_field,
+                table.sps_angle,
+            );
+            row.push(number(rho.filter(|rho| rho.is_finite()), 2));
+        }
+        if columns.max_excitation {
+            let excitation = reaction.excitation_at_rho(
+                table.rho_min,
+                table.beam_energy,
+                table.magnetic_field,
+                table.sps_angle,
+            );
+            row.push(number(
+                excitation.filter(|excitation| *excitation >= 0.0),
+                2,
+            ));
+        }
+        lines.push(format!(r"{} \\", row.join(" & ")));
+    }
+
+    lines.push(r"\hline".to_string());
+    lines.push(r"\end{tabular}".to_string());
+    lines.join("\n") + "\n"
+}
+
+// "12C(d,p)13C" becomes "$^{12}$C(d,p)$^{13}$C", anything else LaTeX treats specially is escaped
+fn latex_reaction(identifier: &str) -> String {
+    let mut latex = String::new();
+    let mut digits = String::new();
+    for c in identifier.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        if !digits.is_empty() {
+            if c.is_ascii_uppercase() {
+                latex.push_str(&format!("$^{{{}}}$", digits));
+            } else {
+                latex.push_str(&digits);
+            }
+            digits.clear();
+        }
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                latex.push('\\');
+                latex.push(c);
+            }
+            '~' => latex.push_str(r"\textasciitilde{}"),
+            '^' => latex.push_str(r"\textasciicircum{}"),
+            '\\' => latex.push_str(r"\textbackslash{}"),
+            _ => latex.push(c),
+        }
+    }
+    latex + &digits
+}
+
+// What the SVG export draws, rho runs along x with the reactions stacked upwards
+pub struct SvgPlot<'a> {
+    pub reactions: &'a [Reaction],
+    pub rows: &'a [(usize, f64, bool)], // (index, baseline, secondary), as stacked on screen
+    pub rho_range: (f64, f64),          // cm, the x axis
+    pub acceptance: (f64, f64),         // cm
+    pub acceptance_color: Color32,
+    pub bar_height: f64,
+    pub reaction_spacing: f64,
+}
+
+const SVG_WIDTH: f64 = 1000.0;
+const SVG_MARGIN: [f64; 4] = [20.0, 220.0, 50.0, 20.0]; // top, right, bottom, left
+const SVG_ROW_HEIGHT: f64 = 60.0; // px per unit of reaction spacing
+
+// Redraws the focal plane plot as vectors, egui only gives us pixels
+pub fn plot_svg(plot: &SvgPlot<'_>) -> String {
+    let [top, right, bottom, left] = SVG_MARGIN;
+    let y_top = plot
+        .rows
+        .iter()
+        .map(|(_, y, _)| *y)
+        .fold(0.25 - plot.reaction_spacing, f64::max)
+        + plot.reaction_spacing
+        + 0.75;
+    let plot_width = SVG_WIDTH - left - right;
+    let plot_height = (y_top + 1.0) * SVG_ROW_HEIGHT;
+    let height = top + plot_height + bottom;
+
+    let (rho_low, rho_high) = plot.rho_range;
+    let x = |rho: f64| left + (rho - rho_low) / (rho_high - rho_low) * plot_width;
+    let y = |offset: f64| top + (y_top - offset) / (y_top + 1.0) * plot_height;
+
+    let mut svg = vec![format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+        w = SVG_WIDTH,
+        h = height
+    )];
+    svg.push(format!(
+        r#"<rect width="{}" height="{}" fill="white"/>"#,
+        SVG_WIDTH, height
+    ));
+    svg.push(format!(
+        r#"<clipPath id="plot-area"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+        left, top, plot_width, plot_height
+    ));
+    svg.push(r#"<g clip-path="url(#plot-area)">"#.to_string());
+
+    let (rho_min, rho_max) = plot.acceptance;
+    svg.push(format!(
+        r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {}/>"#,
+        x(rho_min),
+        top,
+        x(rho_max) - x(rho_min),
+        plot_height,
+        svg_paint("fill", plot.acceptance_color)
+    ));
+    for rho in [rho_min, rho_max] {
+        svg.push(svg_line(
+            x(rho),
+            top,
+            x(rho),
+            top + plot_height,
+            Color32::RED,
+            1.0,
+        ));
+    }
+
+    for &(index, base, _) in plot.rows {
+        let reaction = &plot.reactions[index];
+        if !reaction.visible {
+            continue;
+        }
+        for value in &reaction.rho_values {
+            if !value.rho.is_finite() {
+                continue;
+            }
+            let line = svg_line(
+                x(value.rho),
+                y(base),
+                x(value.rho),
+                y(base + plot.bar_height),
+                reaction.color,
+                1.5,
+            );
+            svg.push(match reaction.style.dash_array() {
+                Some(dashes) => line.replace("/>", &format!(r#" stroke-dasharray="{}"/>"#, dashes)),
+                None => line,
+            });
+        }
+    }
+    svg.push("</g>".to_string());
+
+    // rho axis with 1, 2, 5 spaced ticks
+    let axis_y = top + plot_height;
+    svg.push(svg_line(
+        left,
+        axis_y,
+        left + plot_width,
+        axis_y,
+        Color32::BLACK,
+        1.0,
+    ));
+    let rough_step = (rho_high - rho_low) / 10.0;
+    let magnitude = 10f64.powf(rough_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough_step)
+        .unwrap_or(10.0 * magnitude);
+    let decimals = (-step.log10()).ceil().max(0.0) as usize;
+    for tick in (rho_low / step).ceil() as i64..=(rho_high / step).floor() as i64 {
+        let rho = tick as f64 * step;
+        svg.push(svg_line(
+            x(rho),
+            axis_y,
+            x(rho),
+            axis_y + 5.0,
+            Color32::BLACK,
+            1.0,
+        ));
+        svg.push(format!(
+            r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">{:.*}</text>"#,
+            x(rho),
+            axis_y + 18.0,
+            decimals,
+            rho
+        ));
+    }
+    svg.push(format!(
+        r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">Rho (cm)</text>"#,
+        left + plot_width / 2.0,
+        axis_y + 38.0
+    ));
+
+    // legend to the right of the plot, one entry per reaction
+    for (index, reaction) in plot.reactions.iter().enumerate() {
+        let entry_y = top + 10.0 + index as f64 * 18.0;
+        let legend_x = left + plot_width + 15.0;
+        svg.push(format!(
+            r#"<rect x="{:.2}" y="{:.2}" width="10" height="10" {}/>"#,
+            legend_x,
+            entry_y - 9.0,
+            svg_paint("fill", reaction.color)
+        ));
+        svg.push(format!(
+            r#"<text x="{:.2}" y="{:.2}">{}</text>"#,
+            legend_x + 15.0,
+            entry_y,
+            xml_escape(&reaction.reaction_identifier)
+        ));
+    }
+
+    svg.push("</svg>".to_string());
+    svg.join("\n") + "\n"
+}
+
+fn svg_line(x1: f64, y1: f64, x2: f64, y2: f64, color: Color32, width: f64) -> String {
+    format!(
+        r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke-width="{}" {}/>"#,
+        x1,
+        y1,
+        x2,
+        y2,
+        width,
+        svg_paint("stroke", color)
+    )
+}
+
+// egui colors are premultiplied, SVG wants the straight color and its opacity
+fn svg_paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!(
+        r#"{attribute}="rgb({},{},{})" {attribute}-opacity="{:.3}""#,
+        r,
+        g,
+        b,
+        a as f64 / 255.0
+    )
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use super::project::load_project;
+
+// Calculates every reaction in a saved project and writes the rho table to `output`, or stdout
+pub fn run(project: &Path, output: Option<&Path>) -> Result<(), String> {
+    let mut app = load_project(project)?;
+
+    let (csv, warnings) = app.rho_table();
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+
+    match output {
+        Some(path) => std::fs::write(path, csv)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", csv);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::f64::consts::PI;
+
+use super::nuclear_data_amdc_2016::NuclearData;
+
+pub const C: f64 = 299792458.0; // Speed of light in m/s
+pub const QBRHO2P: f64 = 1.0E-9 * C; // Converts qbrho to momentum (p) (kG*cm -> MeV/c)
+
+// Unit the magnetic field is shown in, the kinematics work in kG
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum FieldUnit {
+    #[default]
+    Kilogauss,
+    Tesla,
+}
+
+impl FieldUnit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FieldUnit::Kilogauss => "kG",
+            FieldUnit::Tesla => "T",
+        }
+    }
+
+    fn per_kilogauss(self) -> f64 {
+        match self {
+            FieldUnit::Kilogauss => 1.0,
+            FieldUnit::Tesla => 0.1,
+        }
+    }
+
+    pub fn convert(self, field: f64) -> f64 {
+        field * self.per_kilogauss()
+    }
+
+    pub fn to_kilogauss(self, field: f64) -> f64 {
+        field / self.per_kilogauss()
+    }
+}
+
+// Unit rho is shown in, the kinematics work in cm
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum RhoUnit {
+    #[default]
+    Centimeter,
+    Meter,
+}
+
+impl RhoUnit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RhoUnit::Centimeter => "cm",
+            RhoUnit::Meter => "m",
+        }
+    }
+
+    fn per_centimeter(self) -> f64 {
+        match self {
+            RhoUnit::Centimeter => 1.0,
+            RhoUnit::Meter => 0.01,
+        }
+    }
+
+    pub fn convert(self, rho: f64) -> f64 {
+        rho * self.per_centimeter()
+    }
+
+    pub fn to_centimeter(self, rho: f64) -> f64 {
+        rho / self.per_centimeter()
+    }
+}
+
+// Converts a rigidity in kG*cm to the display units, e.g. T*m
+pub fn brho_in(brho: f64, field_unit: FieldUnit, rho_unit: RhoUnit) -> f64 {
+    rho_unit.convert(field_unit.convert(brho))
+}
+
+// Full Δp/p of the focal plane relative to its central momentum. p = qBρ, so at a fixed
+// field this is just the spread in rho and doesn't depend on the field or the charge.
+pub fn momentum_acceptance(rho_min: f64, rho_max: f64) -> f64 {
+    2.0 * (rho_max - rho_min) / (rho_max + rho_min)
+}
+
+// The four nuclei of a reaction target(projectile,ejectile)resid
+#[derive(Clone, Copy, Debug)]
+pub struct ReactionNuclei<'a> {
+    pub target: &'a NuclearData,
+    pub projectile: &'a NuclearData,
+    pub ejectile: &'a NuclearData,
+    pub resid: &'a NuclearData,
+}
+
+impl ReactionNuclei<'_> {
+    pub fn q_value(&self) -> f64 {
+        self.target.mass + self.projectile.mass - self.ejectile.mass - self.resid.mass
+    }
+
+    // Why rho can't be calculated, rho = Bρ / B would divide by zero
+    pub fn rho_problem(&self, magnetic_field: f64) -> Option<&'static str> {
+        if self.ejectile.z == 0 {
+            Some("Neutral ejectile has no rho, the spectrograph can't bend it")
+        } else if magnetic_field <= 0.0 {
+            Some("Set a nonzero magnetic field")
+        } else {
+            None
+        }
+    }
+
+    // Minimum beam energy (MeV) to populate `excitation`, zero for exothermic reactions
+    pub fn threshold(&self, excitation: f64) -> f64 {
+        let (projectile, ejectile, resid) = (self.projectile, self.ejectile, self.resid);
+
+        let reaction_q_value = self.q_value() - excitation;
+        let threshold = -reaction_q_value * (ejectile.mass + resid.mass)
+            / (ejectile.mass + resid.mass - projectile.mass);
+        threshold.max(0.0)
+    }
+
+    // sqrt(ejectile energy) = term1 ± sqrt(term1² + term2), term1 carries the angle
+    fn energy_terms(&self, excitation: f64, beam_energy: f64, sps_angle: f64) -> (f64, f64) {
+        let (projectile, ejectile, resid) = (self.projectile, self.ejectile, self.resid);
+
+        let reaction_q_value = self.q_value() - excitation;
+        let beam_reaction_energy = beam_energy; // could put energy loss through target here
+
+        let term1 = (projectile.mass * ejectile.mass * beam_reaction_energy).sqrt()
+            / (ejectile.mass + resid.mass)
+            * (sps_angle * PI / 180.0).cos();
+        let term2 = (beam_reaction_energy * (resid.mass - projectile.mass)
+            + resid.mass * reaction_q_value)
+            / (ejectile.mass + resid.mass);
+
+        (term1, term2)
+    }
+
+    // Kinetic energies (MeV) of the ejectile at the spectrometer angle, fastest first. When the
+    // beam is heavier than the residual, as in inverse kinematics, term2 < 0 and there is a slow
+    // second solution up to the maximum angle and none past it.
+    pub fn ejectile_energies(&self, excitation: f64, beam_energy: f64, sps_angle: f64) -> Vec<f64> {
+        let (term1, term2) = self.energy_terms(excitation, beam_energy, sps_angle);
+        let root = (term1 * term1 + term2).sqrt();
+
+        [term1 + root, term1 - root]
+            .into_iter()
+            .take(if root > 0.0 { 2 } else { 1 })
+            .filter(|speed| *speed > 0.0)
+            .map(|speed| speed * speed)
+            .collect()
+    }
+
+    // Kinetic energy (MeV) of the ejectile at the spectrometer angle with the residual left at `excitation`,
+    // the fast solution when there are two and NaN past the maximum angle
+    pub fn ejectile_energy(&self, excitation: f64, beam_energy: f64, sps_angle: f64) -> f64 {
+        self.ejectile_energies(excitation, beam_energy, sps_angle)
+            .first()
+            .copied()
+            .unwrap_or(f64::NAN)
+    }
+
+    // Largest lab angle (degrees) of the ejectile when its energy is double valued, None when
+    // every angle has a single solution
+    pub fn max_ejectile_angle(&self, excitation: f64, beam_energy: f64) -> Option<f64> {
+        let (forward, term2) = self.energy_terms(excitation, beam_energy, 0.0);
+        if term2 >= 0.0 {
+            return None;
+        }
+        if forward <= 0.0 {
+            return Some(0.0); // no solution at any angle
+        }
+        let cos_max = (-term2).sqrt() / forward;
+        Some(if cos_max > 1.0 {
+            0.0 // not even at 0°
+        } else {
+            cos_max.acos() * 180.0 / PI
+        })
+    }
+
+    // Momentum (MeV/c) of the ejectile at the spectrometer angle
+    pub fn momentum(&self, excitation: f64, beam_energy: f64, sps_angle: f64) -> f64 {
+        let ejectile_energy = self.ejectile_energy(excitation, beam_energy, sps_angle);
+        (ejectile_energy * (ejectile_energy + 2.0 * self.ejectile.mass)).sqrt()
+    }
+
+    // Magnetic rigidity (kG*cm) of the ejectile, fully stripped
+    pub fn brho(&self, excitation: f64, beam_energy: f64, sps_angle: f64) -> f64 {
+        let qbrho = self.momentum(excitation, beam_energy, sps_angle) / QBRHO2P;
+        qbrho / self.ejectile.z as f64
+    }
+
+    // Radius of curvature (cm) of the ejectile in the spectrometer field (kG)
+    pub fn rho(
+        &self,
+        excitation: f64,
+        beam_energy: f64,
+        magnetic_field: f64,
+        sps_angle: f64,
+    ) -> f64 {
+        self.brho(excitation, beam_energy, sps_angle) / magnetic_field
+    }
+
+    // Inverse of rho by bisection, rho falls monotonically with excitation energy
+    pub fn excitation_at_rho(
+        &self,
+        rho: f64,
+        beam_energy: f64,
+        magnetic_field: f64,
+        sps_angle: f64,
+    ) -> Option<f64> {
+        if self.rho_problem(magnetic_field).is_some() {
+            return None;
+        }
+
+        let (mut low, mut high) = (-50.0, 200.0); // MeV
+
+        if self.rho(low, beam_energy, magnetic_field, sps_angle) < rho {
+            return None;
+        }
+
+        for _ in 0..60 {
+            let mid = 0.5 * (low + high);
+            // NaN means the level is kinematically forbidden
+            if self.rho(mid, beam_energy, magnetic_field, sps_angle) >= rho {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        Some(low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::nuclear_data_amdc_2016::MassEvaluation;
+    use super::*;
+
+    // 12C(d,p)13C, as (Z, A) of the target, projectile, ejectile and residual
+    const D_P: [(u32, u32); 4] = [(6, 12), (1, 2), (1, 1), (6, 13)];
+
+    fn masses(particles: [(u32, u32); 4]) -> [NuclearData; 4] {
+        particles.map(|(z, a)| NuclearData::get_data(z, a, MassEvaluation::Ame2016).unwrap())
+    }
+
+    #[test]
+    fn rho_in_tesla_and_meters_matches_kilogauss_and_centimeters() {
+        let [target, projectile, ejectile, resid] = masses(D_P);
+        let nuclei = ReactionNuclei {
+            target: &target,
+            projectile: &projectile,
+            ejectile: &ejectile,
+            resid: &resid,
+        };
+
+        for excitation in [0.0, 3.089] {
+            let rho = nuclei.rho(excitation, 16.0, 8.7, 35.0);
+            let field = FieldUnit::Tesla.to_kilogauss(0.87);
+            let rho_si = RhoUnit::Meter.convert(nuclei.rho(excitation, 16.0, field, 35.0));
+            assert!(
+                (rho_si * 100.0 - rho).abs() < 1e-9,
+                "{} m vs {} cm",
+                rho_si,
+                rho
+            );
+        }
+    }
+
+    #[test]
+    fn brho_in_tesla_meters_matches_momentum() {
+        let [target, projectile, ejectile, resid] = masses(D_P);
+        let nuclei = ReactionNuclei {
+            target: &target,
+            projectile: &projectile,
+            ejectile: &ejectile,
+            resid: &resid,
+        };
+
+        // Bρ (T m) = p (GeV/c) / 0.299792458 for a singly charged ejectile
+        let momentum = nuclei.momentum(0.0, 16.0, 35.0);
+        let brho_kg_cm = nuclei.brho(0.0, 16.0, 35.0);
+        let brho = brho_in(brho_kg_cm, FieldUnit::Tesla, RhoUnit::Meter);
+        assert!((brho - momentum / 1000.0 / 0.299792458).abs() < 1e-9);
+        assert!((brho * 1000.0 - brho_kg_cm).abs() < 1e-6);
+    }
+
+    #[test]
+    fn heavy_ejectiles_have_two_energies_up_to_the_maximum_angle() {
+        // 12C beam picking up the neutron of a deuterium target, the 13C goes forward
+        let [target, projectile, ejectile, resid] = masses([(1, 2), (6, 12), (6, 13), (1, 1)]);
+        let nuclei = ReactionNuclei {
+            target: &target,
+            projectile: &projectile,
+            ejectile: &ejectile,
+            resid: &resid,
+        };
+        let beam_energy = 72.0;
+
+        let max_angle = nuclei.max_ejectile_angle(0.0, beam_energy).unwrap();
+        assert!(max_angle > 0.0 && max_angle < 10.0, "{}°", max_angle);
+
+        // both solutions conserve energy and momentum
+        let sps_angle = max_angle / 2.0;
+        let energies = nuclei.ejectile_energies(0.0, beam_energy, sps_angle);
+        assert_eq!(energies.len(), 2);
+        assert!(energies[0] > energies[1]);
+        for ejectile_energy in energies {
+            let beam_momentum = 2.0 * projectile.mass * beam_energy;
+            let ejectile_momentum = 2.0 * ejectile.mass * ejectile_energy;
+            let resid_momentum = beam_momentum + ejectile_momentum
+                - 2.0 * (beam_momentum * ejectile_momentum).sqrt() * sps_angle.to_radians().cos();
+            let resid_energy = resid_momentum / (2.0 * resid.mass);
+            let missing = beam_energy + nuclei.q_value() - ejectile_energy - resid_energy;
+            assert!(missing.abs() < 1e-6, "{} MeV unaccounted for", missing);
+        }
+
+        assert!(nuclei
+            .ejectile_energies(0.0, beam_energy, max_angle + 1.0)
+            .is_empty());
+        assert!(nuclei.rho(0.0, beam_energy, 12.0, max_angle + 1.0).is_nan());
+
+        let [target, projectile, ejectile, resid] = masses(D_P);
+        let normal = ReactionNuclei {
+            target: &target,
+            projectile: &projectile,
+            ejectile: &ejectile,
+            resid: &resid,
+        };
+        assert_eq!(normal.max_ejectile_angle(0.0, 16.0), None);
+        assert_eq!(normal.ejectile_energies(0.0, 16.0, 35.0).len(), 1);
+    }
+}
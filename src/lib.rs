@@ -2,5 +2,17 @@
 
 mod app;
 pub use app::SPSPlotApp;
+#[cfg(not(target_arch = "wasm32"))]
+mod ensdf;
+mod excitation_fetcher;
 mod excitation_levels_nndc;
+#[cfg(not(target_arch = "wasm32"))]
+mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+mod kinematics;
 mod nuclear_data_amdc_2016;
+mod nuclear_data_ame2020;
+#[cfg(not(target_arch = "wasm32"))]
+mod project;
+mod spectrum;
@@ -6,6 +6,21 @@
 fn main() -> eframe::Result<()> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    // `sps_plot --headless project.json [output.csv]` prints the rho table without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--headless") {
+        let Some(project) = args.get(1) else {
+            eprintln!("usage: sps_plot --headless <project.json> [output.csv]");
+            std::process::exit(2);
+        };
+        let output = args.get(2).map(std::path::Path::new);
+        if let Err(error) = sps_plot::headless::run(std::path::Path::new(project), output) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([425.0, 250.0])
@@ -1,8 +1,50 @@
 use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::nuclear_data_ame2020;
 
 const U2MEV: f64 = 931.49410242;
 const ELECTRON_MASS: f64 = 0.51099895000; //MeV
 
+// Which atomic mass evaluation the masses are read from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum MassEvaluation {
+    // bundled, so the default until AME2020 is too
+    #[default]
+    Ame2016,
+    // falls back to 2016 until a mass_1.mas20 file has been loaded
+    Ame2020,
+}
+
+impl MassEvaluation {
+    // the evaluation masses actually come from
+    pub fn in_use(&self) -> MassEvaluation {
+        match self {
+            MassEvaluation::Ame2020 if nuclear_data_ame2020::is_loaded() => MassEvaluation::Ame2020,
+            _ => MassEvaluation::Ame2016,
+        }
+    }
+}
+
+// Where a mass came from, kept with the data so a plot's provenance can be reported
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum MassSource {
+    #[default]
+    Ame2016,
+    Ame2020,
+    User, // typed in as an override
+}
+
+impl MassSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MassSource::Ame2016 => "AMDC 2016",
+            MassSource::Ame2020 => "AME2020 (mass_1.mas20)",
+            MassSource::User => "user supplied",
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct NuclearData {
     pub z: u32,
@@ -10,6 +52,8 @@ pub struct NuclearData {
     pub mass: f64,
     pub isotope: String,
     pub element: String,
+    #[serde(default)]
+    pub source: MassSource,
 }
 
 impl Default for NuclearData {
@@ -20,42 +64,134 @@ impl Default for NuclearData {
             mass: 0.0,
             isotope: String::from("None"),
             element: String::from("None"),
+            source: MassSource::default(),
         }
     }
 }
 
 impl NuclearData {
-    pub fn get_data(z: u32, a: u32) -> Option<NuclearData> {
-        let n = a - z; // neutron number
+    pub fn get_data(z: u32, a: u32, evaluation: MassEvaluation) -> Option<NuclearData> {
+        let n = a.checked_sub(z)?; // neutron number
         let mut data = NuclearData::default();
-        let isotopes = excitation_levels_nndc();
 
-        if let Some((a, el, atomic_mass_base, atomic_mass_micro_u)) = isotopes.get(&(n, z)) {
-            data.z = z;
-            data.a = *a;
-            data.mass = (*atomic_mass_base as f64 + atomic_mass_micro_u / 1_000_000.0) * U2MEV
-                - (z as f64) * ELECTRON_MASS;
-            data.isotope = format!("{}{}", a, el);
-            data.element = el.to_string();
+        let (a, el, atomic_mass) = match evaluation {
+            MassEvaluation::Ame2020 if nuclear_data_ame2020::is_loaded() => {
+                data.source = MassSource::Ame2020;
+                nuclear_data_ame2020::get(n, z)?
+            }
+            _ => {
+                data.source = MassSource::Ame2016;
+                let isotopes = mass_table();
+                let (a, el, atomic_mass_base, atomic_mass_micro_u) = isotopes.get(&(n, z))?;
+                (
+                    *a,
+                    el.to_string(),
+                    *atomic_mass_base as f64 + atomic_mass_micro_u / 1_000_000.0,
+                )
+            }
+        };
+
+        data.z = z;
+        data.a = a;
+        data.mass = atomic_mass * U2MEV - (z as f64) * ELECTRON_MASS;
+        data.isotope = format!("{}{}", a, el);
+        data.element = el;
+
+        log::info!("Z: {}", data.z);
+        log::info!("A: {}", data.a);
+        log::info!("Found element: {}", data.element);
+        log::info!("Found isotope: {}", data.isotope);
+        log::info!("Found mass: {}", data.mass);
+
+        Some(data)
+    }
 
-            log::info!("Z: {}", data.z);
-            log::info!("A: {}", data.a);
-            log::info!("Found element: {}", data.element);
-            log::info!("Found isotope: {}", data.isotope);
-            log::info!("Found mass: {}", data.mass);
+    // A nucleus with a user supplied nuclear mass (MeV), for ones missing from the table
+    pub fn with_mass(z: u32, a: u32, mass: f64) -> NuclearData {
+        let element = element_symbols()
+            .into_iter()
+            .find(|(_, symbol_z)| *symbol_z == z)
+            .map_or(format!("Z{}", z), |(symbol, _)| symbol.to_string());
 
-            return Some(data);
+        NuclearData {
+            z,
+            a,
+            mass,
+            isotope: format!("{}{}", a, element),
+            element,
+            source: MassSource::User,
         }
-        None
     }
 }
 
+// Element symbol -> Z, built from the mass table
+pub fn element_symbols() -> HashMap<&'static str, u32> {
+    mass_table()
+        .iter()
+        .map(|(&(_, z), &(_, el, _, _))| (el, z))
+        .collect()
+}
+
+// Parses names like "12C", "C12" or "208Pb" (and p, d, t, a or α, n) into (Z, A)
+pub fn parse_isotope(name: &str) -> Result<(u32, u32), String> {
+    let name = name.trim();
+    match name {
+        "n" => return Ok((0, 1)),
+        "p" => return Ok((1, 1)),
+        "d" => return Ok((1, 2)),
+        "t" => return Ok((1, 3)),
+        "a" | "α" | "alpha" => return Ok((2, 4)),
+        _ => {}
+    }
+
+    let digits: String = name.chars().filter(char::is_ascii_digit).collect();
+    let symbol: String = name.chars().filter(char::is_ascii_alphabetic).collect();
+    if digits.is_empty()
+        || symbol.is_empty()
+        || (name != format!("{}{}", digits, symbol) && name != format!("{}{}", symbol, digits))
+    {
+        return Err(format!(
+            "Can't read \"{}\", use a name like 12C or C12",
+            name
+        ));
+    }
+
+    let a = digits
+        .parse::<u32>()
+        .map_err(|_| format!("Mass number {} is too large", digits))?;
+
+    // exact case first so "14N" isn't read as a neutron
+    let symbols = element_symbols();
+    let z = symbols.get(symbol.as_str()).copied().or_else(|| {
+        symbols
+            .iter()
+            .find(|(el, _)| el.eq_ignore_ascii_case(&symbol))
+            .map(|(_, z)| *z)
+    });
+    let z = z.ok_or_else(|| format!("Unknown element \"{}\"", symbol))?;
+
+    if a < z {
+        return Err(format!("{} has A = {} smaller than Z = {}", name, a, z));
+    }
+
+    Ok((z, a))
+}
+
+type MassTable = HashMap<(u32, u32), (u32, &'static str, i32, f64)>;
+
+// Building the table takes a few ms and every Calculate looks up four masses per reaction,
+// so it is built once on first use
+fn mass_table() -> &'static MassTable {
+    static TABLE: OnceLock<MassTable> = OnceLock::new();
+    TABLE.get_or_init(excitation_levels_nndc)
+}
+
 // I am sorry if anyone looks at this... i hate it too
 // i couldnt figure out how to get a text file when compiling to the web
 
 // key=(neutron, proton) with value=(atomic number, element, atomic_mass_base, atomic_mass_micro_u)
 #[rustfmt::skip]
-pub fn excitation_levels_nndc() -> HashMap<(u32, u32), (u32, &'static str, i32, f64)> {
+pub fn excitation_levels_nndc() -> MassTable {
     let mut map = HashMap::new();
 
    map.insert((1, 0), (1, "n", 1, 008664.91582));
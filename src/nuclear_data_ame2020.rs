@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// (mass number, element, atomic mass in u)
+type MassEntry = (u32, String, f64);
+
+// key=(neutron, proton)
+type MassTable = HashMap<(u32, u32), MassEntry>;
+
+// AME2020 isn't bundled, it is read from the mass_1.mas20 file published by the AMDC
+static TABLE: RwLock<Option<MassTable>> = RwLock::new(None);
+
+// mass_1.mas20 is fixed width, values with '#' in place of the decimal point are estimates
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_mas20_line(line: &str) -> Option<((u32, u32), MassEntry)> {
+    let field = |start: usize, end: usize| -> Option<String> {
+        line.get(start..end.min(line.len()))
+            .map(|value| value.trim().replace('#', "."))
+    };
+
+    let n = field(4, 9)?.parse::<u32>().ok()?;
+    let z = field(9, 14)?.parse::<u32>().ok()?;
+    let a = field(14, 19)?.parse::<u32>().ok()?;
+    let element = field(20, 23)?;
+    let base = field(106, 109)?.parse::<f64>().ok()?;
+    let micro_u = field(110, 123)?.parse::<f64>().ok()?;
+
+    // header lines don't parse, this catches anything that slipped through
+    (n + z == a && !element.is_empty())
+        .then_some(((n, z), (a, element, base + micro_u / 1_000_000.0)))
+}
+
+// Replaces the loaded table, returns the number of nuclei read
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(contents: &str) -> Result<usize, String> {
+    let table = contents
+        .lines()
+        .filter_map(parse_mas20_line)
+        .collect::<MassTable>();
+    if table.is_empty() {
+        return Err("No masses found, expected the AME2020 mass_1.mas20 file".to_string());
+    }
+
+    let count = table.len();
+    *TABLE.write().unwrap() = Some(table);
+    Ok(count)
+}
+
+pub fn is_loaded() -> bool {
+    TABLE.read().unwrap().is_some()
+}
+
+pub fn get(n: u32, z: u32) -> Option<MassEntry> {
+    TABLE.read().unwrap().as_ref()?.get(&(n, z)).cloned()
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    // header and nuclide lines of mass_1.mas20
+    const MAS20: &str = "\
+1N-Z    N    Z   A  EL    O     MASS EXCESS(keV)     BINDING ENERGY/A (keV)        BETA-DECAY ENERGY(keV)      ATOMIC MASS(micro-u)
+0 -1    0    1    1 H          7288.971064    0.000013          0.0        0.0 B-            *              1 007825.031898    0.000014
+0  0    1    1    2 H         13135.722895    0.000015    1112.2831     0.0000 B-            *              2 014101.777844    0.000015
+0  0    6    6   12 C                  0.0         0.0    7680.1446     0.0002 B-  -17338.0681     1.0000  12      000000.0         0.0
+   1    7    6   13 C           3125.00875     0.00021    7469.8495     0.0002 B-   -2220.4718     0.2695  13 003354.835336    0.000252";
+
+    #[test]
+    fn mas20_lines_are_read() {
+        let table = MAS20
+            .lines()
+            .filter_map(parse_mas20_line)
+            .collect::<MassTable>();
+        assert_eq!(table.len(), 4);
+
+        let (a, element, mass) = &table[&(7, 6)];
+        assert_eq!((*a, element.as_str()), (13, "C"));
+        assert!((mass - 13.003354835336).abs() < 1e-12, "{} u", mass);
+        assert_eq!(table[&(6, 6)].2, 12.0);
+        assert!((table[&(1, 1)].2 - 2.014101777844).abs() < 1e-12);
+    }
+
+    #[test]
+    fn estimated_masses_keep_their_value() {
+        // '#' marks a value from systematics and stands in for the decimal point
+        let line = MAS20
+            .lines()
+            .nth(4)
+            .unwrap()
+            .replace("003354.835336", "003354#835336");
+        let (_, (_, _, mass)) = parse_mas20_line(&line).unwrap();
+        assert!((mass - 13.003354835336).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use super::app::SPSPlotApp;
+
+// bump when a change to SPSPlotApp can't be read by older versions
+pub const PROJECT_VERSION: u32 = 1;
+
+#[derive(serde::Serialize)]
+struct ProjectRef<'a> {
+    version: u32,
+    app: &'a SPSPlotApp,
+}
+
+#[derive(serde::Deserialize)]
+struct Project {
+    version: u32,
+    app: SPSPlotApp,
+}
+
+pub fn save_project(app: &SPSPlotApp, path: &Path) -> Result<(), String> {
+    let project = ProjectRef {
+        version: PROJECT_VERSION,
+        app,
+    };
+
+    let json = serde_json::to_string_pretty(&project).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn load_project(path: &Path) -> Result<SPSPlotApp, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("{} is not valid JSON: {}", path.display(), e))?;
+
+    match value.get("version").and_then(|version| version.as_u64()) {
+        None => {
+            return Err(format!(
+                "{} is not an SPS Plot project (missing version)",
+                path.display()
+            ))
+        }
+        Some(version) if version > PROJECT_VERSION as u64 => {
+            return Err(format!(
+                "{} was saved by a newer version of SPS Plot (project version {}, supported {})",
+                path.display(),
+                version,
+                PROJECT_VERSION
+            ))
+        }
+        Some(_) => {}
+    }
+
+    let project: Project = serde_json::from_value(value)
+        .map_err(|e| format!("{} is not a compatible project: {}", path.display(), e))?;
+    log::info!(
+        "Loaded project version {} from {}",
+        project.version,
+        path.display()
+    );
+
+    Ok(project.app)
+}
@@ -0,0 +1,186 @@
+// Parses a focal plane histogram with a channel and a counts column per line.
+// A single column is read as counts with the line index as the channel.
+// Blank lines, '#' comments and a non-numeric header are ignored, returns the bins and the number of malformed lines.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_spectrum_csv(contents: &str) -> (Vec<[f64; 2]>, usize) {
+    let mut bins = Vec::new();
+    let mut skipped = 0;
+
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let columns = line
+            .split(|c: char| c == ',' || c == '\t' || c == ' ')
+            .map(str::trim)
+            .filter(|column| !column.is_empty())
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<_>, _>>();
+
+        match columns.as_deref() {
+            Ok([counts]) => bins.push([bins.len() as f64, *counts]),
+            Ok([channel, counts, ..]) => bins.push([*channel, *counts]),
+            _ if line_number == 0 => {} // header
+            _ => skipped += 1,
+        }
+    }
+
+    (bins, skipped)
+}
+
+// Estimates the background under each bin as the smallest count within `window` channels
+// and removes it, negative results are clamped to zero.
+pub fn subtract_background(bins: &[[f64; 2]], window: usize) -> Vec<[f64; 2]> {
+    let half = window / 2;
+    (0..bins.len())
+        .map(|index| {
+            let start = index.saturating_sub(half);
+            let end = (index + half + 1).min(bins.len());
+            let background = bins[start..end]
+                .iter()
+                .map(|[_, counts]| *counts)
+                .fold(f64::INFINITY, f64::min);
+            let [channel, counts] = bins[index];
+            [channel, (counts - background).max(0.0)]
+        })
+        .collect()
+}
+
+// Least squares polynomial through (x, y) points, returns the coefficients lowest order first.
+// None when there are too few points or they don't constrain the fit (e.g. repeated x).
+pub fn fit_polynomial(points: &[[f64; 2]], degree: usize) -> Option<Vec<f64>> {
+    let size = degree + 1;
+    if points.len() < size {
+        return None;
+    }
+
+    // channels run to thousands, so x is centered and scaled to [-1, 1] to keep the normal
+    // equations well conditioned
+    let center = points.iter().map(|[x, _]| x).sum::<f64>() / points.len() as f64;
+    let scale = points
+        .iter()
+        .map(|[x, _]| (x - center).abs())
+        .fold(0.0, f64::max);
+    if scale == 0.0 && degree > 0 {
+        return None;
+    }
+    let scale = scale.max(1.0);
+
+    // normal equations, augmented with the right hand side
+    let mut matrix = vec![vec![0.0; size + 1]; size];
+    for [x, y] in points {
+        let t = (x - center) / scale;
+        for (row, equation) in matrix.iter_mut().enumerate() {
+            for (column, term) in equation.iter_mut().take(size).enumerate() {
+                *term += t.powi((row + column) as i32);
+            }
+            equation[size] += y * t.powi(row as i32);
+        }
+    }
+
+    // Gaussian elimination with partial pivoting, the diagonal sums up to one per point
+    let tolerance = 1e-12 * points.len() as f64;
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() < tolerance {
+            return None;
+        }
+        matrix.swap(column, pivot);
+
+        for row in 0..size {
+            if row != column {
+                let factor = matrix[row][column] / matrix[column][column];
+                for k in column..=size {
+                    matrix[row][k] -= factor * matrix[column][k];
+                }
+            }
+        }
+    }
+
+    // expand sum c_k ((x - center) / scale)^k back into powers of x
+    let mut coefficients = vec![0.0; size];
+    let mut power = vec![1.0]; // ((x - center) / scale)^k, lowest order first
+    for (k, equation) in matrix.iter().enumerate() {
+        let c = equation[size] / equation[k];
+        for (coefficient, term) in coefficients.iter_mut().zip(&power) {
+            *coefficient += c * term;
+        }
+        let mut next = vec![0.0; power.len() + 1];
+        for (j, term) in power.iter().enumerate() {
+            next[j + 1] += term / scale;
+            next[j] -= term * center / scale;
+        }
+        power = next;
+    }
+    Some(coefficients)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+        coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c)
+    }
+
+    #[test]
+    fn cubic_calibration_over_the_full_detector_is_recovered() {
+        let truth = [50.0, 0.01, 2e-6, -3e-10]; // rho (cm) from channel
+        let points = (0..14)
+            .map(|i| {
+                let channel = 100.0 + 300.0 * i as f64;
+                [channel, evaluate(&truth, channel)]
+            })
+            .collect::<Vec<_>>();
+
+        let fit = fit_polynomial(&points, 3).unwrap();
+        for (fitted, expected) in fit.iter().zip(truth) {
+            assert!(((fitted - expected) / expected).abs() < 1e-6, "{:?}", fit);
+        }
+        for channel in [0.0, 2048.0, 4095.0] {
+            assert!((evaluate(&fit, channel) - evaluate(&truth, channel)).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn underconstrained_fits_are_refused() {
+        assert_eq!(fit_polynomial(&[[1.0, 2.0]], 1), None);
+        assert_eq!(
+            fit_polynomial(&[[3.0, 1.0], [3.0, 2.0], [3.0, 3.0]], 1),
+            None
+        );
+
+        let line = fit_polynomial(&[[1000.0, 60.0], [3000.0, 80.0]], 1).unwrap();
+        assert!((line[0] - 50.0).abs() < 1e-9 && (line[1] - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn background_is_the_local_minimum() {
+        let bins = (0..20)
+            .map(|channel| [channel as f64, if channel == 10 { 110.0 } else { 10.0 }])
+            .collect::<Vec<_>>();
+
+        let subtracted = subtract_background(&bins, 5);
+        assert_eq!(subtracted.len(), bins.len());
+        assert_eq!(subtracted[10], [10.0, 100.0]);
+        assert!(subtracted
+            .iter()
+            .enumerate()
+            .all(|(index, [_, counts])| index == 10 || *counts == 0.0));
+    }
+
+    #[test]
+    fn spectrum_files_with_one_or_two_columns_are_read() {
+        let (bins, skipped) =
+            parse_spectrum_csv("channel,counts\n# run 42\n0,5\n1, 7\n\n2\tbad\n3 9\n");
+        assert_eq!(bins, [[0.0, 5.0], [1.0, 7.0], [3.0, 9.0]]);
+        assert_eq!(skipped, 1);
+
+        let (bins, skipped) = parse_spectrum_csv("4\n6\n8\n");
+        assert_eq!(bins, [[0.0, 4.0], [1.0, 6.0], [2.0, 8.0]]);
+        assert_eq!(skipped, 0);
+    }
+}